@@ -0,0 +1,233 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Regression benchmark for `Lattice` construction and search, added
+//! alongside the struct-of-arrays rewrite of its per-node Viterbi bookkeeping
+//! (`total_cost`/`right_id`/`prev_non_ws_right_id`) so future changes to this
+//! hot path can be measured against a realistic multi-kilobyte sentence
+//! instead of guessed at. Also benchmarks a standalone reimplementation of
+//! the pre-rewrite array-of-structs `VNode` layout it replaced
+//! ([`OldLattice`]), so the difference between the two layouts is measured
+//! here rather than assumed.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use sudachi::analysis::inner::{Node, NodeIdx};
+use sudachi::analysis::lattice::Lattice;
+use sudachi::analysis::node::LatticeNode;
+use sudachi::dic::connect::ConnectionMatrix;
+use sudachi::dic::word_id::WordId;
+
+const LEFT_RIGHT_IDS: u16 = 64;
+const NONE_RIGHT_ID: u16 = u16::MAX;
+
+/// One padded array-of-structs entry, reconstructed exactly as it was
+/// before the struct-of-arrays rewrite: `total_cost: i32` next to two
+/// `u16`s, 8 bytes per node with no padding.
+#[allow(dead_code)]
+struct OldVNode {
+    total_cost: i32,
+    right_id: u16,
+    prev_non_ws_right_id: u16,
+}
+
+/// Standalone reimplementation of `Lattice` as it was before the
+/// struct-of-arrays rewrite, kept only in this benchmark so the two memory
+/// layouts can be measured side by side on an identical workload. Not
+/// wired into any production code path.
+#[derive(Default)]
+struct OldLattice {
+    ends: Vec<Vec<OldVNode>>,
+    ends_full: Vec<Vec<Node>>,
+    indices: Vec<Vec<NodeIdx>>,
+    size: usize,
+}
+
+impl OldLattice {
+    fn reset_vec<T>(data: &mut Vec<Vec<T>>, target: usize) {
+        if data.len() < target {
+            data.resize_with(target, Vec::new);
+        }
+        for inner in data.iter_mut().take(target) {
+            inner.clear();
+        }
+    }
+
+    fn reset(&mut self, length: usize) {
+        let target = length + 1;
+        Self::reset_vec(&mut self.ends, target);
+        Self::reset_vec(&mut self.ends_full, target);
+        Self::reset_vec(&mut self.indices, target);
+        self.size = length;
+        self.ends[0].push(OldVNode {
+            total_cost: 0,
+            right_id: 0,
+            prev_non_ws_right_id: NONE_RIGHT_ID,
+        });
+    }
+
+    fn connect_node(&self, r_node: &Node, conn: &ConnectionMatrix) -> (NodeIdx, i32) {
+        let begin = r_node.begin();
+        let node_cost = r_node.cost() as i32;
+        let mut min_cost = i32::MAX;
+        let mut prev_idx = NodeIdx::empty();
+
+        for (i, l_vnode) in self.ends[begin].iter().enumerate() {
+            if l_vnode.total_cost == i32::MAX {
+                continue;
+            }
+            let normal_connect_cost = conn.cost(l_vnode.right_id, r_node.left_id()) as i32;
+            let normal_cost = l_vnode.total_cost + normal_connect_cost + node_cost;
+            if normal_cost < min_cost {
+                min_cost = normal_cost;
+                prev_idx = NodeIdx::new(begin as u16, i as u16);
+            }
+        }
+        (prev_idx, min_cost)
+    }
+
+    fn insert(&mut self, node: Node, conn: &ConnectionMatrix) {
+        let (idx, cost) = self.connect_node(&node, conn);
+        let end_idx = node.end();
+        let prev_non_ws_right_id = if node.is_whitespace() {
+            NONE_RIGHT_ID
+        } else {
+            node.right_id()
+        };
+        self.ends[end_idx].push(OldVNode {
+            total_cost: cost,
+            right_id: node.right_id(),
+            prev_non_ws_right_id,
+        });
+        self.indices[end_idx].push(idx);
+        self.ends_full[end_idx].push(node);
+    }
+
+    /// Minimal analog of `Lattice::connect_eos`: pick the cheapest node
+    /// ending at the final boundary. Kept only so the two layouts do the
+    /// same total amount of work per fill, not to produce a usable result.
+    fn connect_eos(&self) -> i32 {
+        self.ends[self.size]
+            .iter()
+            .map(|v| v.total_cost)
+            .min()
+            .unwrap_or(i32::MAX)
+    }
+}
+
+/// A connection matrix over `LEFT_RIGHT_IDS` left/right ids with
+/// deterministic, non-uniform costs.
+fn build_connection_matrix() -> ConnectionMatrix {
+    let n = LEFT_RIGHT_IDS as usize;
+    let raw = vec![0u8; n * n * 2];
+    let mut conn = ConnectionMatrix::from_offset_size(&raw, 0, n, n).unwrap();
+    for left in 0..n as u16 {
+        for right in 0..n as u16 {
+            // Deterministic, non-uniform costs so the Viterbi search has to
+            // actually compare alternatives rather than always taking the
+            // first candidate.
+            let cost = ((left as i32 * 31 + right as i32 * 17) % 200) - 100;
+            conn.update(left, right, cost as i16);
+        }
+    }
+    conn
+}
+
+/// A synthetic but realistic lattice shape: every codepoint boundary has a
+/// handful of overlapping candidate nodes (1, 2 and 3 codepoints long), like
+/// a real sentence would after dictionary lookup, long enough to be a few
+/// kilobytes of Japanese text once decoded.
+fn fill_lattice(lattice: &mut Lattice, conn: &ConnectionMatrix, char_len: usize) {
+    lattice.reset(char_len);
+    for end in 1..=char_len {
+        for word_len in 1..=3usize.min(end) {
+            let begin = end - word_len;
+            let left_id = ((begin * 7 + word_len) % LEFT_RIGHT_IDS as usize) as u16;
+            let right_id = ((end * 11 + word_len) % LEFT_RIGHT_IDS as usize) as u16;
+            let cost = ((begin * 3 + end * 5) % 50) as i16;
+            let word_id = WordId::from_raw((begin * 1000 + end) as u32);
+            let mut node = Node::new(begin as u16, end as u16, left_id, right_id, cost, word_id);
+            node.set_whitespace(word_len == 1 && begin % 9 == 0);
+            lattice.insert(node, "x", conn);
+        }
+    }
+    lattice.connect_eos(conn).unwrap();
+}
+
+/// Same shape as [`fill_lattice`], against the pre-rewrite [`OldLattice`]
+/// layout, so the two can be benchmarked on an identical workload.
+fn fill_old_lattice(lattice: &mut OldLattice, conn: &ConnectionMatrix, char_len: usize) {
+    lattice.reset(char_len);
+    for end in 1..=char_len {
+        for word_len in 1..=3usize.min(end) {
+            let begin = end - word_len;
+            let left_id = ((begin * 7 + word_len) % LEFT_RIGHT_IDS as usize) as u16;
+            let right_id = ((end * 11 + word_len) % LEFT_RIGHT_IDS as usize) as u16;
+            let cost = ((begin * 3 + end * 5) % 50) as i16;
+            let word_id = WordId::from_raw((begin * 1000 + end) as u32);
+            let mut node = Node::new(begin as u16, end as u16, left_id, right_id, cost, word_id);
+            node.set_whitespace(word_len == 1 && begin % 9 == 0);
+            lattice.insert(node, conn);
+        }
+    }
+    black_box(lattice.connect_eos());
+}
+
+fn bench_lattice(c: &mut Criterion) {
+    let conn = build_connection_matrix();
+    // A few thousand codepoints, comparable to a long paragraph.
+    let char_len = 4000usize;
+
+    c.bench_function("lattice_construct_4k_chars", |b| {
+        b.iter(|| {
+            let mut lattice = Lattice::default();
+            fill_lattice(&mut lattice, &conn, char_len);
+            black_box(&lattice);
+        })
+    });
+
+    // Apples-to-apples comparison against the array-of-structs layout the
+    // struct-of-arrays rewrite replaced, on the same synthetic workload, so
+    // the layout change is measured rather than assumed.
+    c.bench_function("lattice_construct_4k_chars_aos_old_layout", |b| {
+        b.iter(|| {
+            let mut lattice = OldLattice::default();
+            fill_old_lattice(&mut lattice, &conn, char_len);
+            black_box(&lattice);
+        })
+    });
+
+    let mut lattice = Lattice::default();
+    fill_lattice(&mut lattice, &conn, char_len);
+
+    c.bench_function("lattice_fill_top_path_4k_chars", |b| {
+        b.iter(|| {
+            let mut path = Vec::new();
+            lattice.fill_top_path(&mut path);
+            black_box(&path);
+        })
+    });
+
+    c.bench_function("lattice_fill_nbest_paths_4k_chars", |b| {
+        b.iter(|| {
+            let mut paths = Vec::new();
+            lattice.fill_nbest_paths(10, &conn, &mut paths);
+            black_box(&paths);
+        })
+    });
+}
+
+criterion_group!(benches, bench_lattice);
+criterion_main!(benches);
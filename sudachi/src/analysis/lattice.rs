@@ -15,7 +15,7 @@
  */
 
 use crate::analysis::inner::{Node, NodeIdx};
-use crate::analysis::node::{LatticeNode, PathCost, RightId};
+use crate::analysis::node::{LatticeNode, RightId};
 use crate::dic::connect::ConnectionMatrix;
 use crate::dic::grammar::Grammar;
 use crate::dic::lexicon_set::LexiconSet;
@@ -24,43 +24,116 @@ use crate::dic::word_id::WordId;
 use crate::error::SudachiResult;
 use crate::input_text::InputBuffer;
 use crate::prelude::SudachiError;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::fmt::{Display, Formatter};
 use std::io::Write;
 
-/// Lattice Node for Viterbi Search.
-/// Extremely small for better cache locality.
-/// Current implementation has 25% efficiency loss because of padding :(
-/// Maybe we should use array-of-structs layout instead, but I want to try to measure the
-/// efficiency of that without the effects of the current rewrite.
-struct VNode {
-    total_cost: i32,
-    right_id: u16,
-    prev_non_ws_right_id: u16,
+/// Sentinel for `prev_non_separator_right_id` meaning "no non-separator
+/// predecessor is known yet" (either this is the BOS sentinel, or every
+/// node on the path back to BOS so far has counted as a separator).
+const NONE_RIGHT_ID: u16 = u16::MAX;
+
+/// Which character classes, beyond plain whitespace, count as a bridgeable
+/// separator surface. Plain whitespace is always bridgeable when a profile
+/// is `enabled`; these classes extend that set to the other separator-like
+/// surfaces real text uses in place of whitespace (ellipses, middle dots,
+/// dotted leaders), so the bridgeable set can be read from analyzer config /
+/// plugin settings instead of being a fixed match arm upstream.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SeparatorClasses {
+    /// `…` and `⋯`.
+    pub ellipsis: bool,
+    /// `・`, the katakana middle dot.
+    pub middle_dot: bool,
+    /// ASCII `.` / full-width `．`, as used in a repeated dotted leader
+    /// (`...`, `．．．`).
+    pub dotted_leader: bool,
 }
 
-impl RightId for VNode {
-    #[inline]
-    fn right_id(&self) -> u16 {
-        self.right_id
+impl SeparatorClasses {
+    /// Every known separator class enabled.
+    pub fn all() -> Self {
+        Self {
+            ellipsis: true,
+            middle_dot: true,
+            dotted_leader: true,
+        }
     }
 }
 
-impl PathCost for VNode {
-    #[inline]
-    fn total_cost(&self) -> i32 {
-        self.total_cost
-    }
+/// Configuration for the whitespace/gap-bridging scorer: whether bridging a
+/// non-separator node across a run of separator nodes is allowed at all, an
+/// extra cost to charge per bridged gap, and which non-whitespace surfaces
+/// count as a bridgeable separator in the first place. `Lattice::insert`
+/// classifies every non-whitespace node's own surface with
+/// [`classifies_as_separator`](Self::classifies_as_separator) rather than
+/// hardcoding the separator set, so callers can tune it from analyzer
+/// config / plugin settings without a code change. `From<bool>` is provided
+/// so existing call sites that pass a bare `true`/`false` keep compiling
+/// with a zero penalty and no extra separator classes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BridgeSeparatorProfile {
+    pub enabled: bool,
+    pub bridge_penalty: i32,
+    pub separator_classes: SeparatorClasses,
 }
 
-impl VNode {
-    const NONE_RIGHT_ID: u16 = u16::MAX;
+impl BridgeSeparatorProfile {
+    pub fn disabled() -> Self {
+        Self::default()
+    }
 
-    #[inline]
-    fn new(right_id: u16, total_cost: i32, prev_non_ws_right_id: u16) -> VNode {
-        VNode {
-            right_id,
-            total_cost,
-            prev_non_ws_right_id,
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            bridge_penalty: 0,
+            separator_classes: SeparatorClasses::default(),
+        }
+    }
+
+    /// Enabled, charging `bridge_penalty` extra cost for every bridged gap.
+    pub fn with_penalty(bridge_penalty: i32) -> Self {
+        Self {
+            enabled: true,
+            bridge_penalty,
+            separator_classes: SeparatorClasses::default(),
+        }
+    }
+
+    /// Replace this profile's separator classes with `separator_classes`;
+    /// plain whitespace stays bridgeable regardless.
+    pub fn with_separator_classes(mut self, separator_classes: SeparatorClasses) -> Self {
+        self.separator_classes = separator_classes;
+        self
+    }
+
+    /// Whether `surface` counts as a bridgeable separator under this
+    /// profile: empty or all-whitespace always does, and a surface made up
+    /// entirely of characters from an enabled [`SeparatorClasses`] member
+    /// also does. Consulted by [`Lattice::insert`](Lattice::insert)/
+    /// [`Lattice::connect_node`](Lattice::connect_node) for every
+    /// non-whitespace node's own surface, instead of a fixed match arm.
+    pub fn classifies_as_separator(&self, surface: &str) -> bool {
+        surface.chars().all(|c| self.char_is_separator(c))
+    }
+
+    fn char_is_separator(&self, c: char) -> bool {
+        if c.is_whitespace() {
+            return true;
+        }
+        (self.separator_classes.ellipsis && matches!(c, '…' | '⋯'))
+            || (self.separator_classes.middle_dot && c == '・')
+            || (self.separator_classes.dotted_leader && matches!(c, '.' | '．'))
+    }
+}
+
+impl From<bool> for BridgeSeparatorProfile {
+    fn from(enabled: bool) -> Self {
+        Self {
+            enabled,
+            bridge_penalty: 0,
+            separator_classes: SeparatorClasses::default(),
         }
     }
 }
@@ -70,23 +143,49 @@ impl VNode {
 /// First level of parallel arrays is indexed by end word boundary.
 /// Word boundaries are always aligned to codepoint boundaries, not to byte boundaries.
 ///
+/// Per-node Viterbi bookkeeping (`total_cost`, `right_id`,
+/// `prev_non_ws_right_id`) used to live together in a single small `VNode`
+/// struct, one per `ends_full` entry. Splitting the three fields into their
+/// own parallel arrays (struct-of-arrays, mirroring how `ends_full`/`indices`
+/// already sit alongside each other) lets `connect_node`'s hot predecessor
+/// loop scan a single contiguous `total_cost` array instead of striding
+/// through 8-byte structs.
+///
 /// During the successive analysis, we do not drop inner vectors, so
 /// the size of vectors never shrink.
 /// You must use the size parameter to check the current size and never
 /// access vectors after the end.
 #[derive(Default)]
 pub struct Lattice {
-    ends: Vec<Vec<VNode>>,
+    /// Minimum cost from BOS to this node, parallel to `ends_full`.
+    total_costs: Vec<Vec<i32>>,
+    /// `right_id` of the node itself, parallel to `ends_full`.
+    right_ids: Vec<Vec<u16>>,
+    /// `right_id` of the closest non-separator node on the best path leading
+    /// into this node (or `NONE_RIGHT_ID`), parallel to `ends_full`. "Separator"
+    /// here is whatever `separator_flags` says, not just `Node::is_whitespace`.
+    prev_non_separator_right_ids: Vec<Vec<u16>>,
+    /// Whether each node counts as a bridgeable separator, parallel to
+    /// `ends_full`: `Node::is_whitespace()`, or (when the surface passed to
+    /// `insert` classifies as one under `global_whitespace_bridge`'s
+    /// [`SeparatorClasses`]) a configured separator character like `・` or
+    /// `…`. Computed once at `insert` time, since that is the one place this
+    /// struct ever sees a node's surface text; `connect_node`'s hot
+    /// predecessor loop only ever reads it back out of this array.
+    separator_flags: Vec<Vec<bool>>,
     ends_full: Vec<Vec<Node>>,
     indices: Vec<Vec<NodeIdx>>,
     eos: Option<(NodeIdx, i32)>,
     size: usize,
-    global_whitespace_bridge: bool,
+    global_whitespace_bridge: BridgeSeparatorProfile,
 }
 
 impl Lattice {
-    pub fn set_global_whitespace_bridge(&mut self, enabled: bool) -> bool {
-        std::mem::replace(&mut self.global_whitespace_bridge, enabled)
+    pub fn set_global_whitespace_bridge(
+        &mut self,
+        profile: impl Into<BridgeSeparatorProfile>,
+    ) -> BridgeSeparatorProfile {
+        std::mem::replace(&mut self.global_whitespace_bridge, profile.into())
     }
 
     /// Number of boundaries in the current lattice.
@@ -119,7 +218,10 @@ impl Lattice {
     /// Prepare lattice for the next analysis of a sentence with the
     /// specified length (in codepoints)
     pub fn reset(&mut self, length: usize) {
-        Self::reset_vec(&mut self.ends, length + 1);
+        Self::reset_vec(&mut self.total_costs, length + 1);
+        Self::reset_vec(&mut self.right_ids, length + 1);
+        Self::reset_vec(&mut self.prev_non_separator_right_ids, length + 1);
+        Self::reset_vec(&mut self.separator_flags, length + 1);
         Self::reset_vec(&mut self.ends_full, length + 1);
         Self::reset_vec(&mut self.indices, length + 1);
         self.eos = None;
@@ -128,7 +230,30 @@ impl Lattice {
     }
 
     fn connect_bos(&mut self) {
-        self.ends[0].push(VNode::new(0, 0, VNode::NONE_RIGHT_ID));
+        self.total_costs[0].push(0);
+        self.right_ids[0].push(0);
+        self.prev_non_separator_right_ids[0].push(NONE_RIGHT_ID);
+        self.separator_flags[0].push(false);
+    }
+
+    /// Whether `node` counts as a bridgeable separator under `profile`:
+    /// always true when `Node::is_whitespace()` already says so, never true
+    /// for the synthetic BOS/EOS sentinel (an empty surface would otherwise
+    /// vacuously satisfy [`classifies_as_separator`](BridgeSeparatorProfile::classifies_as_separator)),
+    /// and otherwise whatever the profile's [`SeparatorClasses`] says about
+    /// `surface`.
+    fn counts_as_bridge_separator(
+        node: &Node,
+        surface: &str,
+        profile: &BridgeSeparatorProfile,
+    ) -> bool {
+        if node.is_whitespace() {
+            true
+        } else if node.is_special_node() {
+            false
+        } else {
+            profile.classifies_as_separator(surface)
+        }
     }
 
     /// Find EOS node -- finish the lattice construction
@@ -137,7 +262,7 @@ impl Lattice {
         let eos_start = (len - 1) as u16;
         let eos_end = (len - 1) as u16;
         let node = Node::new(eos_start, eos_end, 0, 0, 0, WordId::EOS);
-        let (idx, cost, _) = self.connect_node(&node, conn);
+        let (idx, cost, _) = self.connect_node(&node, "", conn);
         if cost == i32::MAX {
             Err(SudachiError::EosBosDisconnect)
         } else {
@@ -146,50 +271,73 @@ impl Lattice {
         }
     }
 
-    /// Insert a single node in the lattice, founding the path to the previous node
+    /// Insert a single node in the lattice, founding the path to the previous node.
+    /// `surface` is the node's own text (by codepoint range `node.begin()..node.end()`),
+    /// used to consult `global_whitespace_bridge`'s configured separator classes;
+    /// it is ignored for nodes where `Node::is_whitespace()` already decides the
+    /// question.
     /// Assumption: lattice for all previous boundaries is already constructed
-    pub fn insert(&mut self, node: Node, conn: &ConnectionMatrix) -> i32 {
-        let (idx, cost, prev_non_ws_right_id) = self.connect_node(&node, conn);
+    pub fn insert(&mut self, node: Node, surface: &str, conn: &ConnectionMatrix) -> i32 {
+        let (idx, cost, prev_non_separator_right_id) = self.connect_node(&node, surface, conn);
         let end_idx = node.end();
-        self.ends[end_idx].push(VNode::new(node.right_id(), cost, prev_non_ws_right_id));
+        let is_separator =
+            Self::counts_as_bridge_separator(&node, surface, &self.global_whitespace_bridge);
+        self.total_costs[end_idx].push(cost);
+        self.right_ids[end_idx].push(node.right_id());
+        self.prev_non_separator_right_ids[end_idx].push(prev_non_separator_right_id);
+        self.separator_flags[end_idx].push(is_separator);
         self.indices[end_idx].push(idx);
         self.ends_full[end_idx].push(node);
         cost
     }
 
-    /// Find the path with the minimal cost through the lattice to the attached node
+    /// Find the path with the minimal cost through the lattice to the attached node.
+    /// `r_surface` is `r_node`'s own text, used the same way as in [`insert`](Self::insert).
     /// Assumption: lattice for all previous boundaries is already constructed
     #[inline]
-    pub fn connect_node(&self, r_node: &Node, conn: &ConnectionMatrix) -> (NodeIdx, i32, u16) {
+    pub fn connect_node(
+        &self,
+        r_node: &Node,
+        r_surface: &str,
+        conn: &ConnectionMatrix,
+    ) -> (NodeIdx, i32, u16) {
         let begin = r_node.begin();
 
         let node_cost = r_node.cost() as i32;
         let mut min_cost = i32::MAX;
         let mut prev_idx = NodeIdx::empty();
-        let mut prev_non_ws_right_id = VNode::NONE_RIGHT_ID;
+        let mut prev_non_separator_right_id = NONE_RIGHT_ID;
+        let r_is_separator =
+            Self::counts_as_bridge_separator(r_node, r_surface, &self.global_whitespace_bridge);
 
-        for (i, l_vnode) in self.ends[begin].iter().enumerate() {
-            if !l_vnode.is_connected_to_bos() {
+        for i in 0..self.total_costs[begin].len() {
+            let l_total_cost = self.total_costs[begin][i];
+            if l_total_cost == i32::MAX {
                 continue;
             }
+            let l_right_id = self.right_ids[begin][i];
+            let l_prev_non_separator_right_id = self.prev_non_separator_right_ids[begin][i];
 
-            let l_node_is_whitespace = if begin == 0 {
+            let l_is_separator = if begin == 0 {
                 false
             } else {
-                self.ends_full[begin][i].is_whitespace()
+                self.separator_flags[begin][i]
             };
-            let normal_connect_cost = conn.cost(l_vnode.right_id(), r_node.left_id()) as i32;
-            let normal_cost = l_vnode.total_cost() + normal_connect_cost + node_cost;
+            let normal_connect_cost = conn.cost(l_right_id, r_node.left_id()) as i32;
+            let normal_cost = l_total_cost + normal_connect_cost + node_cost;
 
             let mut best_cost_for_pred = normal_cost;
-            if self.global_whitespace_bridge
-                && l_node_is_whitespace
-                && !r_node.is_whitespace()
-                && l_vnode.prev_non_ws_right_id != VNode::NONE_RIGHT_ID
+            if self.global_whitespace_bridge.enabled
+                && l_is_separator
+                && !r_is_separator
+                && l_prev_non_separator_right_id != NONE_RIGHT_ID
             {
                 let bridged_connect_cost =
-                    conn.cost(l_vnode.prev_non_ws_right_id, r_node.left_id()) as i32;
-                let bridged_cost = l_vnode.total_cost() + bridged_connect_cost + node_cost;
+                    conn.cost(l_prev_non_separator_right_id, r_node.left_id()) as i32;
+                let bridged_cost = l_total_cost
+                    + bridged_connect_cost
+                    + node_cost
+                    + self.global_whitespace_bridge.bridge_penalty;
                 if bridged_cost < best_cost_for_pred {
                     best_cost_for_pred = bridged_cost;
                 }
@@ -198,26 +346,29 @@ impl Lattice {
             if best_cost_for_pred < min_cost {
                 min_cost = best_cost_for_pred;
                 prev_idx = NodeIdx::new(begin as u16, i as u16);
-                prev_non_ws_right_id = if r_node.is_whitespace() {
-                    l_vnode.prev_non_ws_right_id
+                prev_non_separator_right_id = if r_is_separator {
+                    l_prev_non_separator_right_id
                 } else {
                     r_node.right_id()
                 };
             }
         }
 
-        (prev_idx, min_cost, prev_non_ws_right_id)
+        (prev_idx, min_cost, prev_non_separator_right_id)
     }
 
     /// Checks if there exist at least one at the word end boundary
     pub fn has_previous_node(&self, i: usize) -> bool {
-        self.ends.get(i).map(|d| !d.is_empty()).unwrap_or(false)
+        self.total_costs
+            .get(i)
+            .map(|d| !d.is_empty())
+            .unwrap_or(false)
     }
 
     /// Lookup a node for the index
     pub fn node(&self, id: NodeIdx) -> (&Node, i32) {
         let node = &self.ends_full[id.end() as usize][id.index() as usize];
-        let cost = self.ends[id.end() as usize][id.index() as usize].total_cost;
+        let cost = self.total_costs[id.end() as usize][id.index() as usize];
         (node, cost)
     }
 
@@ -243,6 +394,389 @@ impl Lattice {
             }
         }
     }
+
+    /// Up to `n` lowest-cost complete paths through the lattice, each paired
+    /// with its total cost, in non-decreasing cost order.
+    /// **Attention**: like [`fill_top_path`](Self::fill_top_path), every path
+    /// is in end-to-beginning order and needs to be reversed by the caller.
+    ///
+    /// Implemented as backward A* over the same predecessor/connection-cost
+    /// structure as [`connect_node`](Self::connect_node): each node's
+    /// `total_cost` (the exact minimum cost from BOS, computed once by the
+    /// forward Viterbi pass already performed by `insert`/`connect_eos`) is
+    /// an exact admissible heuristic for a predecessor's distance from BOS,
+    /// so the search frontier yields complete paths in non-decreasing total
+    /// cost order. Unlike `fill_top_path`, this needs `conn` because
+    /// reconstructing non-best paths requires re-deriving connection costs
+    /// for predecessors the forward pass didn't end up selecting.
+    pub fn fill_nbest_paths(
+        &self,
+        n: usize,
+        conn: &ConnectionMatrix,
+        result: &mut Vec<(Vec<NodeIdx>, i32)>,
+    ) {
+        result.clear();
+        if n == 0 || self.eos.is_none() {
+            return;
+        }
+
+        // A partial path being explored backward from EOS toward BOS.
+        // `g` is the cost of every node/connection from the node just after
+        // `idx` (exclusive) through EOS; `idx`'s own cost is added lazily
+        // when the entry is popped, so it's counted exactly once even
+        // though the same node can be reached by several partial paths.
+        // A `complete` entry has reached BOS and `g` is its total cost.
+        struct Entry {
+            f: i32,
+            g: i32,
+            idx: NodeIdx,
+            path: Vec<NodeIdx>,
+            complete: bool,
+        }
+
+        impl PartialEq for Entry {
+            fn eq(&self, other: &Self) -> bool {
+                self.f == other.f
+            }
+        }
+        impl Eq for Entry {}
+        impl PartialOrd for Entry {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Entry {
+            fn cmp(&self, other: &Self) -> Ordering {
+                // BinaryHeap is a max-heap; reverse so the lowest `f` pops first.
+                other.f.cmp(&self.f)
+            }
+        }
+
+        // Push a heap entry (complete, if it reached BOS) for every predecessor of
+        // `node`, given `g` (the suffix cost already accumulated strictly
+        // after `node`, excluding `node`'s own cost) and `path` (the reversed
+        // path from EOS down to but excluding `node`). Mirrors the
+        // predecessor loop in `connect_node`, including the whitespace-bridge
+        // cost variant, so alternate paths are scored identically to the
+        // forward pass.
+        #[allow(clippy::too_many_arguments)]
+        fn expand(
+            lattice: &Lattice,
+            conn: &ConnectionMatrix,
+            node: &Node,
+            node_is_separator: bool,
+            g: i32,
+            path: &[NodeIdx],
+            heap: &mut BinaryHeap<Entry>,
+        ) {
+            let begin = node.begin();
+            for i in 0..lattice.total_costs[begin].len() {
+                let l_total_cost = lattice.total_costs[begin][i];
+                if l_total_cost == i32::MAX {
+                    continue;
+                }
+                let l_right_id = lattice.right_ids[begin][i];
+                let l_prev_non_separator_right_id = lattice.prev_non_separator_right_ids[begin][i];
+
+                let l_is_separator = if begin == 0 {
+                    false
+                } else {
+                    lattice.separator_flags[begin][i]
+                };
+                let mut connect_cost = conn.cost(l_right_id, node.left_id()) as i32;
+                if lattice.global_whitespace_bridge.enabled
+                    && l_is_separator
+                    && !node_is_separator
+                    && l_prev_non_separator_right_id != NONE_RIGHT_ID
+                {
+                    let bridged_connect_cost =
+                        conn.cost(l_prev_non_separator_right_id, node.left_id()) as i32
+                            + lattice.global_whitespace_bridge.bridge_penalty;
+                    if bridged_connect_cost < connect_cost {
+                        connect_cost = bridged_connect_cost;
+                    }
+                }
+
+                if begin == 0 {
+                    // The sole predecessor at boundary 0 is the BOS sentinel:
+                    // this partial path is complete. It goes back on the heap
+                    // keyed by its exact total cost, so complete paths leave
+                    // the heap in cost order.
+                    heap.push(Entry {
+                        f: g + connect_cost,
+                        g: g + connect_cost,
+                        idx: NodeIdx::empty(),
+                        path: path.to_vec(),
+                        complete: true,
+                    });
+                    continue;
+                }
+
+                let pred_idx = NodeIdx::new(begin as u16, i as u16);
+                let mut pred_path = path.to_vec();
+                pred_path.push(pred_idx);
+                heap.push(Entry {
+                    f: g + connect_cost + l_total_cost,
+                    g: g + connect_cost,
+                    idx: pred_idx,
+                    path: pred_path,
+                    complete: false,
+                });
+            }
+        }
+
+        let mut heap = BinaryHeap::new();
+        // Seed the search from the same virtual EOS node `connect_eos` uses,
+        // so the connection cost from the last real node into EOS is scored
+        // exactly once, the same way it is in the forward pass, instead of
+        // being baked into `self.eos`'s stored cost and then dropped.
+        let eos_node = Node::new(
+            (self.size - 1) as u16,
+            (self.size - 1) as u16,
+            0,
+            0,
+            0,
+            WordId::EOS,
+        );
+        expand(self, conn, &eos_node, false, 0, &[], &mut heap);
+
+        while result.len() < n {
+            let entry = match heap.pop() {
+                Some(e) => e,
+                None => break,
+            };
+            if entry.complete {
+                result.push((entry.path, entry.g));
+                continue;
+            }
+            let (node, _) = self.node(entry.idx);
+            let node_is_separator =
+                self.separator_flags[entry.idx.end() as usize][entry.idx.index() as usize];
+            let g = entry.g + node.cost() as i32;
+            expand(
+                self,
+                conn,
+                node,
+                node_is_separator,
+                g,
+                &entry.path,
+                &mut heap,
+            );
+        }
+    }
+
+    /// Per-node posterior probability, parallel to `ends_full`: for each
+    /// node (indexed the same way as [`nodes_ending_at`](Self::nodes_ending_at)),
+    /// the probability mass that a softmax over every complete BOS-to-EOS
+    /// path (with path cost treated as an unnormalized negative
+    /// log-probability divided by `temperature`) places on paths passing
+    /// through it. Lower `temperature` sharpens the distribution around the
+    /// Viterbi path; a `temperature` of zero or below returns hard Viterbi
+    /// directly (1.0 on the nodes of [`fill_top_path`](Self::fill_top_path)'s
+    /// path, 0.0 elsewhere) instead of dividing by zero.
+    /// Needs `conn` for the same reason [`fill_nbest_paths`](Self::fill_nbest_paths)
+    /// does. Nodes unreachable from BOS or EOS, or a disconnected BOS/EOS
+    /// pair, yield all-zero marginals rather than NaN.
+    pub fn marginal_costs(&self, conn: &ConnectionMatrix, temperature: f32) -> Vec<Vec<f32>> {
+        let mut result: Vec<Vec<f32>> = self
+            .ends_full
+            .iter()
+            .map(|v| vec![0.0f32; v.len()])
+            .collect();
+        if self.eos.is_none() {
+            return result;
+        }
+        if temperature <= 0.0 {
+            let mut path = Vec::new();
+            self.fill_top_path(&mut path);
+            for idx in path {
+                result[idx.end() as usize][idx.index() as usize] = 1.0;
+            }
+            return result;
+        }
+        let t = temperature;
+
+        const NEG_INF: f32 = f32::NEG_INFINITY;
+
+        fn logsumexp(terms: &[f32]) -> f32 {
+            let max = terms.iter().cloned().fold(NEG_INF, f32::max);
+            if max == NEG_INF {
+                return NEG_INF;
+            }
+            let sum: f32 = terms.iter().map(|&x| (x - max).exp()).sum();
+            max + sum.ln()
+        }
+
+        // Cost of the edge from a predecessor (described by its own
+        // right_id/prev_non_separator_right_id/separator flag, since that's
+        // all `connect_node` needs) into `r_node` (with its own separator
+        // flag), including the whitespace-bridge variant. Mirrors `connect_node`.
+        let edge_cost = |l_right_id: u16,
+                         l_is_separator: bool,
+                         l_prev_non_separator: u16,
+                         r_node: &Node,
+                         r_is_separator: bool|
+         -> f32 {
+            let mut cost = conn.cost(l_right_id, r_node.left_id()) as f32;
+            if self.global_whitespace_bridge.enabled
+                && l_is_separator
+                && !r_is_separator
+                && l_prev_non_separator != NONE_RIGHT_ID
+            {
+                let bridged = conn.cost(l_prev_non_separator, r_node.left_id()) as f32
+                    + self.global_whitespace_bridge.bridge_penalty as f32;
+                if bridged < cost {
+                    cost = bridged;
+                }
+            }
+            cost
+        };
+
+        let boundary_count = self.size;
+        let eos_node = Node::new(
+            (boundary_count - 1) as u16,
+            (boundary_count - 1) as u16,
+            0,
+            0,
+            0,
+            WordId::EOS,
+        );
+
+        // Index of successors by the boundary they begin at, so the
+        // backward pass doesn't need to rescan the whole lattice per node.
+        let mut by_begin: Vec<Vec<(usize, usize)>> = vec![Vec::new(); boundary_count];
+        for end in 0..boundary_count {
+            for (i, node) in self.ends_full[end].iter().enumerate() {
+                by_begin[node.begin()].push((end, i));
+            }
+        }
+
+        // Forward pass: alpha(BOS) = 0, alpha[node] = logsumexp over
+        // predecessors l of (alpha[l] - (edge_cost(l, node) + node.cost())/T).
+        // This includes `node`'s own cost, exactly once.
+        let mut alpha: Vec<Vec<f32>> = self
+            .ends_full
+            .iter()
+            .map(|v| vec![NEG_INF; v.len()])
+            .collect();
+        for end in 0..boundary_count {
+            for i in 0..self.ends_full[end].len() {
+                let node = &self.ends_full[end][i];
+                let begin = node.begin();
+                let node_cost = node.cost() as f32;
+                let mut terms = Vec::new();
+                for j in 0..self.total_costs[begin].len() {
+                    if self.total_costs[begin][j] == i32::MAX {
+                        continue;
+                    }
+                    let alpha_pred = if begin == 0 { 0.0 } else { alpha[begin][j] };
+                    if alpha_pred == NEG_INF {
+                        continue;
+                    }
+                    let l_is_separator = if begin == 0 {
+                        false
+                    } else {
+                        self.separator_flags[begin][j]
+                    };
+                    let cost = edge_cost(
+                        self.right_ids[begin][j],
+                        l_is_separator,
+                        self.prev_non_separator_right_ids[begin][j],
+                        node,
+                        self.separator_flags[end][i],
+                    );
+                    terms.push(alpha_pred - (cost + node_cost) / t);
+                }
+                alpha[end][i] = logsumexp(&terms);
+            }
+        }
+
+        let log_z = {
+            let begin = eos_node.begin();
+            let mut terms = Vec::new();
+            for j in 0..self.total_costs[begin].len() {
+                if self.total_costs[begin][j] == i32::MAX {
+                    continue;
+                }
+                let alpha_pred = if begin == 0 { 0.0 } else { alpha[begin][j] };
+                if alpha_pred == NEG_INF {
+                    continue;
+                }
+                let l_is_separator = if begin == 0 {
+                    false
+                } else {
+                    self.separator_flags[begin][j]
+                };
+                let cost = edge_cost(
+                    self.right_ids[begin][j],
+                    l_is_separator,
+                    self.prev_non_separator_right_ids[begin][j],
+                    &eos_node,
+                    false,
+                );
+                terms.push(alpha_pred - cost / t);
+            }
+            logsumexp(&terms)
+        };
+        if log_z == NEG_INF {
+            return result;
+        }
+
+        // Backward pass: beta(EOS) = 0, beta[node] = logsumexp over
+        // successors r of (beta[r] - (edge_cost(node, r) + r.cost())/T).
+        // This adds the connection cost and the *successor's* cost, so
+        // `node`'s own cost is never counted here (only in `alpha`).
+        let mut beta: Vec<Vec<f32>> = self
+            .ends_full
+            .iter()
+            .map(|v| vec![NEG_INF; v.len()])
+            .collect();
+        for end in (0..boundary_count).rev() {
+            for i in 0..self.ends_full[end].len() {
+                let node = &self.ends_full[end][i];
+                let l_right_id = self.right_ids[end][i];
+                let l_prev_non_separator_right_id = self.prev_non_separator_right_ids[end][i];
+                let l_is_separator = self.separator_flags[end][i];
+                let mut terms = Vec::new();
+
+                if end == boundary_count - 1 {
+                    let cost = edge_cost(
+                        l_right_id,
+                        l_is_separator,
+                        l_prev_non_separator_right_id,
+                        &eos_node,
+                        false,
+                    );
+                    terms.push(-cost / t);
+                }
+
+                for &(succ_end, succ_i) in &by_begin[end] {
+                    let beta_succ = beta[succ_end][succ_i];
+                    if beta_succ == NEG_INF {
+                        continue;
+                    }
+                    let succ_node = &self.ends_full[succ_end][succ_i];
+                    let cost = edge_cost(
+                        l_right_id,
+                        l_is_separator,
+                        l_prev_non_separator_right_id,
+                        succ_node,
+                        self.separator_flags[succ_end][succ_i],
+                    );
+                    terms.push(beta_succ - (cost + succ_node.cost() as f32) / t);
+                }
+
+                beta[end][i] = logsumexp(&terms);
+            }
+        }
+
+        for end in 0..boundary_count {
+            for i in 0..self.ends_full[end].len() {
+                result[end][i] = (alpha[end][i] + beta[end][i] - log_z).exp();
+            }
+        }
+        result
+    }
 }
 
 #[cfg(test)]
@@ -302,22 +836,22 @@ mod tests {
 
         let mut plain = Lattice::default();
         plain.reset(3);
-        plain.insert(make_node(0, 1, 0, 1, 0, 1, false), &conn);
-        plain.insert(make_node(0, 1, 0, 2, 1, 2, false), &conn);
-        plain.insert(make_node(1, 2, 1, 9, 0, 11, true), &conn);
-        plain.insert(make_node(1, 2, 2, 9, 0, 12, true), &conn);
-        plain.insert(make_node(2, 3, 3, 4, 0, 21, false), &conn);
+        plain.insert(make_node(0, 1, 0, 1, 0, 1, false), "x", &conn);
+        plain.insert(make_node(0, 1, 0, 2, 1, 2, false), "x", &conn);
+        plain.insert(make_node(1, 2, 1, 9, 0, 11, true), "x", &conn);
+        plain.insert(make_node(1, 2, 2, 9, 0, 12, true), "x", &conn);
+        plain.insert(make_node(2, 3, 3, 4, 0, 21, false), "x", &conn);
         plain.connect_eos(&conn).unwrap();
         assert_eq!(vec![1, 11, 21], path_word_ids(&plain));
 
         let mut bridged = Lattice::default();
         bridged.set_global_whitespace_bridge(true);
         bridged.reset(3);
-        bridged.insert(make_node(0, 1, 0, 1, 0, 1, false), &conn);
-        bridged.insert(make_node(0, 1, 0, 2, 1, 2, false), &conn);
-        bridged.insert(make_node(1, 2, 1, 9, 0, 11, true), &conn);
-        bridged.insert(make_node(1, 2, 2, 9, 0, 12, true), &conn);
-        bridged.insert(make_node(2, 3, 3, 4, 0, 21, false), &conn);
+        bridged.insert(make_node(0, 1, 0, 1, 0, 1, false), "x", &conn);
+        bridged.insert(make_node(0, 1, 0, 2, 1, 2, false), "x", &conn);
+        bridged.insert(make_node(1, 2, 1, 9, 0, 11, true), "x", &conn);
+        bridged.insert(make_node(1, 2, 2, 9, 0, 12, true), "x", &conn);
+        bridged.insert(make_node(2, 3, 3, 4, 0, 21, false), "x", &conn);
         bridged.connect_eos(&conn).unwrap();
         assert_eq!(vec![2, 12, 21], path_word_ids(&bridged));
     }
@@ -340,25 +874,317 @@ mod tests {
 
         let mut plain = Lattice::default();
         plain.reset(3);
-        plain.insert(make_node(0, 1, 0, 1, 0, 1, false), &conn);
-        plain.insert(make_node(0, 1, 0, 2, 1, 2, false), &conn);
-        plain.insert(make_node(1, 2, 1, 9, 0, 11, true), &conn);
-        plain.insert(make_node(1, 2, 2, 9, 0, 12, true), &conn);
-        plain.insert(make_node(2, 3, 3, 4, 0, 21, false), &conn);
+        plain.insert(make_node(0, 1, 0, 1, 0, 1, false), "x", &conn);
+        plain.insert(make_node(0, 1, 0, 2, 1, 2, false), "x", &conn);
+        plain.insert(make_node(1, 2, 1, 9, 0, 11, true), "x", &conn);
+        plain.insert(make_node(1, 2, 2, 9, 0, 12, true), "x", &conn);
+        plain.insert(make_node(2, 3, 3, 4, 0, 21, false), "x", &conn);
         plain.connect_eos(&conn).unwrap();
 
         let mut bridged = Lattice::default();
         bridged.set_global_whitespace_bridge(true);
         bridged.reset(3);
-        bridged.insert(make_node(0, 1, 0, 1, 0, 1, false), &conn);
-        bridged.insert(make_node(0, 1, 0, 2, 1, 2, false), &conn);
-        bridged.insert(make_node(1, 2, 1, 9, 0, 11, true), &conn);
-        bridged.insert(make_node(1, 2, 2, 9, 0, 12, true), &conn);
-        bridged.insert(make_node(2, 3, 3, 4, 0, 21, false), &conn);
+        bridged.insert(make_node(0, 1, 0, 1, 0, 1, false), "x", &conn);
+        bridged.insert(make_node(0, 1, 0, 2, 1, 2, false), "x", &conn);
+        bridged.insert(make_node(1, 2, 1, 9, 0, 11, true), "x", &conn);
+        bridged.insert(make_node(1, 2, 2, 9, 0, 12, true), "x", &conn);
+        bridged.insert(make_node(2, 3, 3, 4, 0, 21, false), "x", &conn);
         bridged.connect_eos(&conn).unwrap();
 
         assert_eq!(path_word_ids(&plain), path_word_ids(&bridged));
     }
+
+    #[test]
+    fn whitespace_bridge_penalty_can_outweigh_bridging_benefit() {
+        let n = 16usize;
+        let raw = vec![0u8; n * n * 2];
+        let mut conn = ConnectionMatrix::from_offset_size(&raw, 0, n, n).unwrap();
+
+        // Same setup as `whitespace_bridge_can_change_best_path`, where an
+        // unpenalized bridge flips the best path from L1 to L2.
+        conn.update(1, 1, 0);
+        conn.update(2, 1, 100);
+        conn.update(1, 2, 100);
+        conn.update(2, 2, 0);
+
+        conn.update(9, 3, 50);
+        conn.update(1, 3, 100);
+        conn.update(2, 3, 0);
+
+        let mut bridged = Lattice::default();
+        bridged.set_global_whitespace_bridge(BridgeSeparatorProfile::with_penalty(100));
+        bridged.reset(3);
+        bridged.insert(make_node(0, 1, 0, 1, 0, 1, false), "x", &conn);
+        bridged.insert(make_node(0, 1, 0, 2, 1, 2, false), "x", &conn);
+        bridged.insert(make_node(1, 2, 1, 9, 0, 11, true), "x", &conn);
+        bridged.insert(make_node(1, 2, 2, 9, 0, 12, true), "x", &conn);
+        bridged.insert(make_node(2, 3, 3, 4, 0, 21, false), "x", &conn);
+        bridged.connect_eos(&conn).unwrap();
+
+        // A large enough penalty makes the un-bridged normal transition
+        // (50) cheaper than the bridged one (0 + 100), so the path stays on
+        // the L1 branch instead of switching to L2.
+        assert_eq!(vec![1, 11, 21], path_word_ids(&bridged));
+    }
+
+    #[test]
+    fn configured_separator_class_bridges_a_non_whitespace_node() {
+        let n = 16usize;
+        let raw = vec![0u8; n * n * 2];
+        let mut conn = ConnectionMatrix::from_offset_size(&raw, 0, n, n).unwrap();
+
+        // Same cost setup as `whitespace_bridge_can_change_best_path`, but
+        // the middle node is a `・` (middle dot) with `is_whitespace() ==
+        // false`: only the plain whitespace check would leave it permanently
+        // ineligible for bridging, regardless of `global_whitespace_bridge`.
+        conn.update(1, 1, 0); // L1 -> W1
+        conn.update(2, 1, 100);
+        conn.update(1, 2, 100);
+        conn.update(2, 2, 0); // L2 -> W2
+
+        // normal transition across the middle dot is expensive
+        conn.update(9, 3, 50);
+        // bridged cost prefers L2 context
+        conn.update(1, 3, 100);
+        conn.update(2, 3, 0);
+
+        let mut without_middle_dot = Lattice::default();
+        without_middle_dot.set_global_whitespace_bridge(BridgeSeparatorProfile::enabled());
+        without_middle_dot.reset(3);
+        without_middle_dot.insert(make_node(0, 1, 0, 1, 0, 1, false), "a", &conn);
+        without_middle_dot.insert(make_node(0, 1, 0, 2, 1, 2, false), "a", &conn);
+        without_middle_dot.insert(make_node(1, 2, 1, 9, 0, 11, false), "・", &conn);
+        without_middle_dot.insert(make_node(1, 2, 2, 9, 0, 12, false), "・", &conn);
+        without_middle_dot.insert(make_node(2, 3, 3, 4, 0, 21, false), "a", &conn);
+        without_middle_dot.connect_eos(&conn).unwrap();
+        // `middle_dot` isn't in the default `SeparatorClasses`, so the `・`
+        // node doesn't classify as a separator and the path stays on the
+        // cheaper L1 branch.
+        assert_eq!(vec![1, 11, 21], path_word_ids(&without_middle_dot));
+
+        let mut with_middle_dot = Lattice::default();
+        with_middle_dot.set_global_whitespace_bridge(
+            BridgeSeparatorProfile::enabled().with_separator_classes(SeparatorClasses {
+                middle_dot: true,
+                ..SeparatorClasses::default()
+            }),
+        );
+        with_middle_dot.reset(3);
+        with_middle_dot.insert(make_node(0, 1, 0, 1, 0, 1, false), "a", &conn);
+        with_middle_dot.insert(make_node(0, 1, 0, 2, 1, 2, false), "a", &conn);
+        with_middle_dot.insert(make_node(1, 2, 1, 9, 0, 11, false), "・", &conn);
+        with_middle_dot.insert(make_node(1, 2, 2, 9, 0, 12, false), "・", &conn);
+        with_middle_dot.insert(make_node(2, 3, 3, 4, 0, 21, false), "a", &conn);
+        with_middle_dot.connect_eos(&conn).unwrap();
+        // With `middle_dot` enabled, the `・` node classifies as a
+        // bridgeable separator despite `is_whitespace() == false`, so the
+        // bridge flips the best path onto the L2 branch exactly as plain
+        // whitespace would in `whitespace_bridge_can_change_best_path`.
+        assert_eq!(vec![2, 12, 21], path_word_ids(&with_middle_dot));
+    }
+
+    #[test]
+    fn bridge_separator_profile_classifies_whitespace_without_any_class_enabled() {
+        let profile = BridgeSeparatorProfile::enabled();
+        assert!(profile.classifies_as_separator(""));
+        assert!(profile.classifies_as_separator(" \t　"));
+        assert!(!profile.classifies_as_separator("…"));
+        assert!(!profile.classifies_as_separator("・"));
+        assert!(!profile.classifies_as_separator("..."));
+    }
+
+    #[test]
+    fn bridge_separator_profile_separator_classes_are_independently_configurable() {
+        let ellipsis_only =
+            BridgeSeparatorProfile::enabled().with_separator_classes(SeparatorClasses {
+                ellipsis: true,
+                ..SeparatorClasses::default()
+            });
+        assert!(ellipsis_only.classifies_as_separator("…⋯"));
+        assert!(!ellipsis_only.classifies_as_separator("・"));
+        assert!(!ellipsis_only.classifies_as_separator("..."));
+
+        let all = BridgeSeparatorProfile::enabled().with_separator_classes(SeparatorClasses::all());
+        assert!(all.classifies_as_separator("…"));
+        assert!(all.classifies_as_separator("・"));
+        assert!(all.classifies_as_separator("...．．．"));
+        assert!(!all.classifies_as_separator("東"));
+    }
+
+    #[test]
+    fn nbest_paths_come_out_in_non_decreasing_cost_order_and_agree_with_top_path() {
+        let n = 16usize;
+        let raw = vec![0u8; n * n * 2];
+        let mut conn = ConnectionMatrix::from_offset_size(&raw, 0, n, n).unwrap();
+
+        conn.update(1, 1, 0);
+        conn.update(2, 1, 50);
+        conn.update(1, 2, 50);
+        conn.update(2, 2, 0);
+        conn.update(1, 3, 0);
+        conn.update(2, 3, 0);
+
+        let mut lattice = Lattice::default();
+        lattice.reset(2);
+        // Two alternative first tokens (word 1 cheaper than word 2), one
+        // second token, so there are exactly two complete paths: [1, 21]
+        // (cheap) and [2, 21] (expensive because of the 50-cost mismatch).
+        lattice.insert(make_node(0, 1, 0, 1, 0, 1, false), "x", &conn);
+        lattice.insert(make_node(0, 1, 0, 2, 0, 2, false), "x", &conn);
+        lattice.insert(make_node(1, 2, 1, 3, 0, 21, false), "x", &conn);
+        lattice.connect_eos(&conn).unwrap();
+
+        let mut top = Vec::new();
+        lattice.fill_top_path(&mut top);
+        top.reverse();
+        let top_word_ids: Vec<u32> = top.iter().map(|i| lattice.node(*i).0.word_id().as_raw()).collect();
+
+        let mut nbest = Vec::new();
+        lattice.fill_nbest_paths(2, &conn, &mut nbest);
+
+        assert_eq!(nbest.len(), 2);
+        assert!(nbest[0].1 <= nbest[1].1);
+
+        let mut best_path = nbest[0].0.clone();
+        best_path.reverse();
+        let best_word_ids: Vec<u32> = best_path
+            .iter()
+            .map(|i| lattice.node(*i).0.word_id().as_raw())
+            .collect();
+        assert_eq!(best_word_ids, top_word_ids);
+        assert_eq!(nbest[0].1, lattice.eos.unwrap().1);
+    }
+
+    #[test]
+    fn marginal_costs_sum_to_one_per_boundary_and_favor_the_best_path() {
+        let n = 16usize;
+        let raw = vec![0u8; n * n * 2];
+        let mut conn = ConnectionMatrix::from_offset_size(&raw, 0, n, n).unwrap();
+
+        conn.update(1, 1, 0);
+        conn.update(2, 1, 50);
+        conn.update(1, 2, 50);
+        conn.update(2, 2, 0);
+        conn.update(1, 3, 0);
+        conn.update(2, 3, 0);
+
+        let mut lattice = Lattice::default();
+        lattice.reset(2);
+        lattice.insert(make_node(0, 1, 0, 1, 0, 1, false), "x", &conn);
+        lattice.insert(make_node(0, 1, 0, 2, 0, 2, false), "x", &conn);
+        lattice.insert(make_node(1, 2, 1, 3, 0, 21, false), "x", &conn);
+        lattice.connect_eos(&conn).unwrap();
+
+        let marginals = lattice.marginal_costs(&conn, 0.01);
+
+        // Every complete path passes through exactly one node at each
+        // boundary, so each boundary's marginals should sum to ~1.
+        assert!((marginals[1].iter().sum::<f32>() - 1.0).abs() < 1e-3);
+        assert!((marginals[2].iter().sum::<f32>() - 1.0).abs() < 1e-3);
+
+        // Word 1 is on the cheaper path; at a low temperature almost all of
+        // the mass should land on it rather than word 2.
+        assert!(marginals[1][0] > 0.99);
+        assert!(marginals[1][1] < 0.01);
+        assert!(marginals[2][0] > 0.99);
+    }
+
+    #[test]
+    fn marginal_costs_are_zero_when_eos_is_disconnected() {
+        let n = 4usize;
+        let raw = vec![0u8; n * n * 2];
+        let conn = ConnectionMatrix::from_offset_size(&raw, 0, n, n).unwrap();
+
+        let mut lattice = Lattice::default();
+        lattice.reset(1);
+        lattice.insert(make_node(0, 1, 0, 1, 0, 1, false), "x", &conn);
+        // No `connect_eos` call, so `self.eos` stays `None`.
+
+        let marginals = lattice.marginal_costs(&conn, 1.0);
+        assert_eq!(marginals, vec![vec![], vec![0.0]]);
+    }
+}
+
+/// Resolved display data for a node's part-of-speech, shared by every
+/// `Lattice::dump*` variant so they stay consistent with each other.
+enum PosData<'a> {
+    Bos,
+    Borrow(&'a [String]),
+}
+
+impl Display for PosData<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PosData::Bos => write!(f, "BOS/EOS"),
+            PosData::Borrow(data) => {
+                for (i, s) in data.iter().enumerate() {
+                    write!(f, "{}", s)?;
+                    if i + 1 != data.len() {
+                        write!(f, ", ")?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Surface text and part-of-speech for a node, resolved the same way for
+/// every dump variant: OOV nodes look up their POS by raw id, dictionary
+/// nodes resolve it through `lexicon`, and BOS/EOS placeholders get a fixed
+/// label.
+fn node_surface_pos<'i, 'g>(
+    r_node: &Node,
+    input: &'i InputBuffer,
+    grammar: &'g Grammar,
+    lexicon: &LexiconSet,
+) -> SudachiResult<(&'i str, PosData<'g>)> {
+    if r_node.is_special_node() {
+        Ok(("(null)", PosData::Bos))
+    } else if r_node.is_oov() {
+        let pos_id = r_node.word_id().word() as usize;
+        Ok((
+            input.curr_slice_c(r_node.begin()..r_node.end()),
+            PosData::Borrow(&grammar.pos_list[pos_id]),
+        ))
+    } else {
+        let winfo = lexicon.get_word_info_subset(r_node.word_id(), InfoSubset::POS_ID)?;
+        Ok((
+            input.orig_slice_c(r_node.begin()..r_node.end()),
+            PosData::Borrow(&grammar.pos_list[winfo.pos_id() as usize]),
+        ))
+    }
+}
+
+/// Escape a string for embedding in a double-quoted DOT label.
+fn dot_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape a string for embedding in a double-quoted JSON string literal.
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            _ => out.push(c),
+        }
+    }
+    out
 }
 
 impl Lattice {
@@ -369,48 +1195,11 @@ impl Lattice {
         lexicon: &LexiconSet,
         out: &mut W,
     ) -> SudachiResult<()> {
-        enum PosData<'a> {
-            Bos,
-            Borrow(&'a [String]),
-        }
-
-        impl Display for PosData<'_> {
-            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-                match self {
-                    PosData::Bos => write!(f, "BOS/EOS"),
-                    PosData::Borrow(data) => {
-                        for (i, s) in data.iter().enumerate() {
-                            write!(f, "{}", s)?;
-                            if i + 1 != data.len() {
-                                write!(f, ", ")?;
-                            }
-                        }
-                        Ok(())
-                    }
-                }
-            }
-        }
-
         let mut dump_idx = 0;
 
         for boundary in (0..self.indices.len()).rev() {
             for r_node in &self.ends_full[boundary] {
-                let (surface, pos) = if r_node.is_special_node() {
-                    ("(null)", PosData::Bos)
-                } else if r_node.is_oov() {
-                    let pos_id = r_node.word_id().word() as usize;
-                    (
-                        input.curr_slice_c(r_node.begin()..r_node.end()),
-                        PosData::Borrow(&grammar.pos_list[pos_id]),
-                    )
-                } else {
-                    let winfo =
-                        lexicon.get_word_info_subset(r_node.word_id(), InfoSubset::POS_ID)?;
-                    (
-                        input.orig_slice_c(r_node.begin()..r_node.end()),
-                        PosData::Borrow(&grammar.pos_list[winfo.pos_id() as usize]),
-                    )
-                };
+                let (surface, pos) = node_surface_pos(r_node, input, grammar, lexicon)?;
 
                 write!(
                     out,
@@ -428,8 +1217,8 @@ impl Lattice {
 
                 let conn = grammar.conn_matrix();
 
-                for l_node in &self.ends[r_node.begin()] {
-                    let connect_cost = conn.cost(l_node.right_id(), r_node.left_id());
+                for &l_right_id in &self.right_ids[r_node.begin()] {
+                    let connect_cost = conn.cost(l_right_id, r_node.left_id());
                     write!(out, " {}", connect_cost)?;
                 }
 
@@ -440,4 +1229,260 @@ impl Lattice {
         }
         Ok(())
     }
+
+    /// `(predecessor, successor)` `(end, index)` pairs for every edge on the
+    /// best path, as found by [`fill_top_path`](Self::fill_top_path). Used by
+    /// `dump_dot`/`dump_json` to flag which edges the Viterbi search
+    /// actually took. Identifies nodes by their raw `(end, index)` pair
+    /// rather than `NodeIdx` itself so the lookup only needs `Eq`/`Hash` on
+    /// primitive integers.
+    fn best_path_edges(&self) -> std::collections::HashSet<((u16, u16), (u16, u16))> {
+        let mut path = Vec::new();
+        self.fill_top_path(&mut path);
+        // `path` is end-to-beginning, so each adjacent pair is (successor, predecessor).
+        path.windows(2)
+            .map(|w| ((w[1].end(), w[1].index()), (w[0].end(), w[0].index())))
+            .collect()
+    }
+
+    /// DOT (Graphviz) export of the lattice: one node per morpheme
+    /// candidate, grouped left-to-right by end boundary via `rank=same`
+    /// clusters, with every inter-node connection drawn as an edge labeled
+    /// by its connection cost. Edges on the best path (per
+    /// [`fill_top_path`](Self::fill_top_path)) are highlighted so
+    /// segmentation decisions are easy to spot once pasted into Graphviz.
+    pub fn dump_dot<W: Write>(
+        &self,
+        input: &InputBuffer,
+        grammar: &Grammar,
+        lexicon: &LexiconSet,
+        out: &mut W,
+    ) -> SudachiResult<()> {
+        let best_edges = self.best_path_edges();
+        // The node adjacent to EOS on the best path is exactly the
+        // predecessor `connect_eos` found, i.e. `self.eos.0`.
+        let best_path_adjacent_to_eos = self.eos.map(|(idx, _)| (idx.end(), idx.index()));
+        // The node adjacent to BOS is the far end of `fill_top_path`'s walk.
+        let best_path_adjacent_to_bos = {
+            let mut path = Vec::new();
+            self.fill_top_path(&mut path);
+            path.last().map(|idx| (idx.end(), idx.index()))
+        };
+        let conn = grammar.conn_matrix();
+
+        writeln!(out, "digraph lattice {{")?;
+        writeln!(out, "  rankdir=LR;")?;
+        writeln!(out, "  node [shape=box];")?;
+        writeln!(out, "  \"BOS\" [shape=ellipse];")?;
+        writeln!(out, "  \"EOS\" [shape=ellipse];")?;
+
+        for boundary in 0..self.indices.len() {
+            if self.ends_full[boundary].is_empty() {
+                continue;
+            }
+            writeln!(out, "  {{ rank=same;")?;
+            for (r_i, r_node) in self.ends_full[boundary].iter().enumerate() {
+                let (surface, pos) = node_surface_pos(r_node, input, grammar, lexicon)?;
+                writeln!(
+                    out,
+                    "    \"{0}_{1}\" [label=\"{2}\\n{3}\\nleft={4} right={5} cost={6}\"];",
+                    boundary,
+                    r_i,
+                    dot_escape(surface),
+                    dot_escape(&pos.to_string()),
+                    r_node.left_id(),
+                    r_node.right_id(),
+                    r_node.cost()
+                )?;
+            }
+            writeln!(out, "  }}")?;
+        }
+
+        for (r_boundary, r_nodes) in self.ends_full.iter().enumerate() {
+            for (r_i, r_node) in r_nodes.iter().enumerate() {
+                let begin = r_node.begin();
+                let r_idx = (r_boundary as u16, r_i as u16);
+                if begin == 0 {
+                    let connect_cost = conn.cost(0, r_node.left_id());
+                    let is_best = best_path_adjacent_to_bos == Some(r_idx);
+                    writeln!(
+                        out,
+                        "  \"BOS\" -> \"{0}_{1}\" [label=\"{2}\"{3}];",
+                        r_boundary,
+                        r_i,
+                        connect_cost,
+                        if is_best { ", color=red, penwidth=2" } else { "" }
+                    )?;
+                    continue;
+                }
+                for (l_i, &l_right_id) in self.right_ids[begin].iter().enumerate() {
+                    let connect_cost = conn.cost(l_right_id, r_node.left_id());
+                    let l_idx = (begin as u16, l_i as u16);
+                    let is_best = best_edges.contains(&(l_idx, r_idx));
+                    writeln!(
+                        out,
+                        "  \"{0}_{1}\" -> \"{2}_{3}\" [label=\"{4}\"{5}];",
+                        begin,
+                        l_i,
+                        r_boundary,
+                        r_i,
+                        connect_cost,
+                        if is_best { ", color=red, penwidth=2" } else { "" }
+                    )?;
+                }
+            }
+        }
+
+        // `right_ids[0]` holds the BOS sentinel, not a real node, so only
+        // walk it here when the last boundary isn't also boundary 0 (i.e.
+        // the lattice has at least one real node to connect into EOS).
+        if self.size > 1 {
+            let eos_left_id = 0u16;
+            for (r_i, &r_right_id) in self.right_ids[self.size - 1].iter().enumerate() {
+                let connect_cost = conn.cost(r_right_id, eos_left_id);
+                let r_idx = ((self.size - 1) as u16, r_i as u16);
+                let is_best = best_path_adjacent_to_eos == Some(r_idx);
+                writeln!(
+                    out,
+                    "  \"{0}_{1}\" -> \"EOS\" [label=\"{2}\"{3}];",
+                    self.size - 1,
+                    r_i,
+                    connect_cost,
+                    if is_best { ", color=red, penwidth=2" } else { "" }
+                )?;
+            }
+        }
+
+        writeln!(out, "}}")?;
+        Ok(())
+    }
+
+    /// JSON export of the lattice with the same node/edge coverage as
+    /// [`dump_dot`](Self::dump_dot), intended to drive an external lattice
+    /// viewer rather than Graphviz. Every node carries a stable `id` of the
+    /// form `"<end>_<index>"` (`"BOS"`/`"EOS"` for the sentinels), and every
+    /// edge records whether it lies on the best path from
+    /// [`fill_top_path`](Self::fill_top_path).
+    pub fn dump_json<W: Write>(
+        &self,
+        input: &InputBuffer,
+        grammar: &Grammar,
+        lexicon: &LexiconSet,
+        out: &mut W,
+    ) -> SudachiResult<()> {
+        let best_edges = self.best_path_edges();
+        // The node adjacent to EOS on the best path is exactly the
+        // predecessor `connect_eos` found, i.e. `self.eos.0`.
+        let best_path_adjacent_to_eos = self.eos.map(|(idx, _)| (idx.end(), idx.index()));
+        // The node adjacent to BOS is the far end of `fill_top_path`'s walk.
+        let best_path_adjacent_to_bos = {
+            let mut path = Vec::new();
+            self.fill_top_path(&mut path);
+            path.last().map(|idx| (idx.end(), idx.index()))
+        };
+        let conn = grammar.conn_matrix();
+
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"boundary_count\": {},", self.size)?;
+        writeln!(out, "  \"nodes\": [")?;
+        writeln!(
+            out,
+            "    {{ \"id\": \"BOS\", \"begin\": 0, \"end\": 0, \"surface\": \"\", \"pos\": \"BOS/EOS\" }},"
+        )?;
+        let mut first = true;
+        for (boundary, r_nodes) in self.ends_full.iter().enumerate() {
+            for (r_i, r_node) in r_nodes.iter().enumerate() {
+                let (surface, pos) = node_surface_pos(r_node, input, grammar, lexicon)?;
+                if !first {
+                    writeln!(out, ",")?;
+                }
+                first = false;
+                write!(
+                    out,
+                    "    {{ \"id\": \"{0}_{1}\", \"begin\": {2}, \"end\": {3}, \"surface\": \"{4}\", \"pos\": \"{5}\", \"left_id\": {6}, \"right_id\": {7}, \"cost\": {8}, \"is_oov\": {9} }}",
+                    boundary,
+                    r_i,
+                    r_node.begin(),
+                    r_node.end(),
+                    json_escape(surface),
+                    json_escape(&pos.to_string()),
+                    r_node.left_id(),
+                    r_node.right_id(),
+                    r_node.cost(),
+                    r_node.is_oov()
+                )?;
+            }
+        }
+        writeln!(out)?;
+        writeln!(
+            out,
+            "    , {{ \"id\": \"EOS\", \"begin\": {0}, \"end\": {0}, \"surface\": \"\", \"pos\": \"BOS/EOS\" }}",
+            self.size - 1
+        )?;
+        writeln!(out, "  ],")?;
+
+        writeln!(out, "  \"edges\": [")?;
+        let mut first = true;
+        for (r_boundary, r_nodes) in self.ends_full.iter().enumerate() {
+            for (r_i, r_node) in r_nodes.iter().enumerate() {
+                let begin = r_node.begin();
+                let r_idx = (r_boundary as u16, r_i as u16);
+                if begin == 0 {
+                    let connect_cost = conn.cost(0, r_node.left_id());
+                    let is_best = best_path_adjacent_to_bos == Some(r_idx);
+                    if !first {
+                        writeln!(out, ",")?;
+                    }
+                    first = false;
+                    write!(
+                        out,
+                        "    {{ \"from\": \"BOS\", \"to\": \"{0}_{1}\", \"cost\": {2}, \"best_path\": {3} }}",
+                        r_boundary, r_i, connect_cost, is_best
+                    )?;
+                    continue;
+                }
+                for (l_i, &l_right_id) in self.right_ids[begin].iter().enumerate() {
+                    let connect_cost = conn.cost(l_right_id, r_node.left_id());
+                    let l_idx = (begin as u16, l_i as u16);
+                    let is_best = best_edges.contains(&(l_idx, r_idx));
+                    if !first {
+                        writeln!(out, ",")?;
+                    }
+                    first = false;
+                    write!(
+                        out,
+                        "    {{ \"from\": \"{0}_{1}\", \"to\": \"{2}_{3}\", \"cost\": {4}, \"best_path\": {5} }}",
+                        begin, l_i, r_boundary, r_i, connect_cost, is_best
+                    )?;
+                }
+            }
+        }
+        // `right_ids[0]` holds the BOS sentinel, not a real node, so only
+        // walk it here when the last boundary isn't also boundary 0 (i.e.
+        // the lattice has at least one real node to connect into EOS).
+        if self.size > 1 {
+            let eos_left_id = 0u16;
+            for (r_i, &r_right_id) in self.right_ids[self.size - 1].iter().enumerate() {
+                let connect_cost = conn.cost(r_right_id, eos_left_id);
+                let r_idx = ((self.size - 1) as u16, r_i as u16);
+                let is_best = best_path_adjacent_to_eos == Some(r_idx);
+                if !first {
+                    writeln!(out, ",")?;
+                }
+                first = false;
+                write!(
+                    out,
+                    "    {{ \"from\": \"{0}_{1}\", \"to\": \"EOS\", \"cost\": {2}, \"best_path\": {3} }}",
+                    self.size - 1,
+                    r_i,
+                    connect_cost,
+                    is_best
+                )?;
+            }
+        }
+        writeln!(out)?;
+        writeln!(out, "  ]")?;
+        writeln!(out, "}}")?;
+        Ok(())
+    }
 }
@@ -18,17 +18,31 @@ use crate::analysis::inner::Node;
 use crate::analysis::lattice::Lattice;
 use crate::analysis::node::{LatticeNode, RightId};
 use crate::dic::connect::ConnectionMatrix;
+use crate::dic::grammar::Grammar;
 use crate::dic::lexicon::word_infos::{WordInfo, WordInfoData};
 use crate::dic::lexicon_set::LexiconSet;
 use crate::dic::subset::InfoSubset;
 use crate::dic::word_id::WordId;
 use crate::error::SudachiResult;
 use crate::input_text::InputBuffer;
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use unicode_normalization::UnicodeNormalization;
 
+/// Default cost charged per edit (insertion/deletion/substitution) absorbed by the
+/// typo-tolerant matcher, so that exact matches keep sorting ahead of near matches.
+pub const DEFAULT_TYPO_PENALTY: i32 = 400;
+
+/// Default softmax temperature for [`ReadingCandidatePath::probability`],
+/// chosen to match the dictionary's connection-cost granularity (the same
+/// scale as [`DEFAULT_TYPO_PENALTY`]) so that a single typo- or
+/// bridge-penalty-sized gap between two paths already separates their
+/// scores noticeably instead of washing out as near-identical floats.
+pub const DEFAULT_COST_TEMPERATURE: f64 = 400.0;
+
 #[derive(Clone, Debug)]
 pub struct ReadingCandidateToken {
     pub word_id: WordId,
@@ -41,6 +55,30 @@ pub struct ReadingCandidateToken {
 #[derive(Clone, Debug)]
 pub struct ReadingCandidatePath {
     pub total_cost: i32,
+    /// Softmax-normalized confidence in `[0.0, 1.0]`, comparable across the
+    /// paths returned alongside this one (they sum to `1.0`), for callers
+    /// that want to threshold or display a score rather than a raw,
+    /// unscaled `total_cost`. See [`ReadingSearchOptions::temperature`].
+    pub probability: f64,
+    /// Count of characters of the query reading this path matched (a path
+    /// is only ever accepted once the whole query is consumed, so today
+    /// this is always the query's full length); exposed directly so
+    /// incremental/IME-style callers re-querying on every keystroke don't
+    /// need to recompute it from the query string they passed in.
+    pub covered_reading_len: usize,
+    /// The surface text beyond this path's last token that the reading
+    /// didn't reach, e.g. the as-yet-untyped continuation of an in-progress
+    /// conversion in an IME-style caller. Empty when the path's tokens cover
+    /// the whole tokenized surface.
+    pub remaining_surface: String,
+    /// Accumulated character-level edit distance (insert/delete/substitute)
+    /// absorbed by [`enumerate_reading_candidates_fuzzy`] to match this
+    /// path's tokens against the query; `0` for an exact match. Spelling
+    /// variation folded away by
+    /// [`ReadingSearchOptions::phonological_fuzzy`] (long vowels,
+    /// small/large kana, dakuten) isn't counted here, since it costs no
+    /// edits in the first place.
+    pub reading_edit_distance: u32,
     pub tokens: Vec<ReadingCandidateToken>,
 }
 
@@ -50,18 +88,137 @@ struct NodeRef {
     index: usize,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+/// A single state of a bounded Levenshtein automaton over the query reading:
+/// `(query_char_index, accumulated_errors)`.
+type AutomatonPair = (u32, u32);
+
+/// Canonicalized, sorted-by-index set of non-dominated automaton states.
+/// Canonical form keeps at most one entry per `query_char_index` (the lowest
+/// `errors` seen for it) and drops any entry whose `errors` exceeds `max_typo`,
+/// so equal automaton states always compare and hash equal.
+type AutomatonState = Vec<AutomatonPair>;
+
+fn canonicalize_automaton(mut state: AutomatonState, max_typo: u32) -> AutomatonState {
+    state.retain(|&(_, errors)| errors <= max_typo);
+    state.sort_unstable_by_key(|&(index, _)| index);
+    let mut result: AutomatonState = Vec::with_capacity(state.len());
+    for (index, errors) in state {
+        match result.last_mut() {
+            Some((last_index, last_errors)) if *last_index == index => {
+                if errors < *last_errors {
+                    *last_errors = errors;
+                }
+            }
+            _ => result.push((index, errors)),
+        }
+    }
+    result
+}
+
+/// Epsilon-closure of the automaton over "insertion" transitions: the query
+/// may contain a character that has no counterpart in the token reading, so we
+/// can advance the query index without consuming a token character, at the
+/// cost of one error. Chains are bounded by `max_typo` since every step spends
+/// one unit of the error budget.
+fn automaton_closure(state: &AutomatonState, query_len: u32, max_typo: u32) -> AutomatonState {
+    let mut closure = state.clone();
+    let mut frontier = state.clone();
+    for _ in 0..=max_typo {
+        let mut next = Vec::new();
+        for &(index, errors) in &frontier {
+            if index < query_len && errors + 1 <= max_typo {
+                next.push((index + 1, errors + 1));
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        closure.extend(next.iter().copied());
+        frontier = next;
+    }
+    canonicalize_automaton(closure, max_typo)
+}
+
+/// Fold a single token character through the automaton, producing the next
+/// state. A dead transition (no surviving pair) is signalled by an empty
+/// result so callers can bail out of a token variant early.
+fn automaton_step(
+    state: &AutomatonState,
+    token_char: char,
+    query: &[char],
+    max_typo: u32,
+) -> AutomatonState {
+    let query_len = query.len() as u32;
+    let pre = automaton_closure(state, query_len, max_typo);
+    let mut next = Vec::new();
+    for &(index, errors) in &pre {
+        if index < query_len {
+            if query[index as usize] == token_char {
+                next.push((index + 1, errors));
+            } else if errors + 1 <= max_typo {
+                // substitution
+                next.push((index + 1, errors + 1));
+            }
+        }
+        if errors + 1 <= max_typo {
+            // deletion: token_char has no counterpart in the query
+            next.push((index, errors + 1));
+        }
+    }
+    canonicalize_automaton(next, max_typo)
+}
+
+/// Fold a whole token match variant through the automaton one character at a
+/// time, returning `None` if the variant cannot be consumed within the
+/// `max_typo` error budget.
+///
+/// When `is_prefix` is set, folding stops as soon as the automaton has
+/// consumed the whole query: the rest of the variant is the as-yet-untyped
+/// continuation of the current token, not something to be charged as edits
+/// against what the user has typed so far.
+fn automaton_consume_variant(
+    state: &AutomatonState,
+    variant: &str,
+    query: &[char],
+    max_typo: u32,
+    is_prefix: bool,
+) -> Option<AutomatonState> {
+    let query_len = query.len() as u32;
+    let mut current = state.clone();
+    for token_char in variant.chars() {
+        current = automaton_step(&current, token_char, query, max_typo);
+        if current.is_empty() {
+            return None;
+        }
+        if is_prefix && automaton_accepts(&current, query_len).is_some() {
+            return Some(current);
+        }
+    }
+    Some(current)
+}
+
+/// Lowest error count among automaton states that have fully consumed the
+/// query, if any.
+fn automaton_accepts(state: &AutomatonState, query_len: u32) -> Option<u32> {
+    state
+        .iter()
+        .filter(|&&(index, _)| index == query_len)
+        .map(|&(_, errors)| errors)
+        .min()
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
 struct SearchState {
     boundary: usize,
     prev_right_id: u16,
-    reading_offset: usize,
+    automaton: AutomatonState,
 }
 
 #[derive(Clone)]
 struct NodeMeta {
     node: Node,
     word_info: WordInfo,
-    match_variants: Vec<String>,
+    match_variants: Vec<MatchVariant>,
 }
 
 impl NodeMeta {
@@ -103,7 +260,443 @@ fn normalize_for_matching(text: &str) -> String {
     lower.chars().map(hira_to_kata).collect::<String>()
 }
 
-fn build_match_variants(word_info: &WordInfo) -> Vec<String> {
+/// The vowel column a katakana character belongs to in the gojūon table,
+/// used to resolve a long-vowel mark into the vowel it extends. Covers the
+/// standard gojūon rows (plain and voiced/semi-voiced); characters outside
+/// it (`ン`, `ッ`, punctuation, etc.) have no vowel and return `None`.
+fn katakana_vowel_column(ch: char) -> Option<char> {
+    match ch {
+        'ア' | 'カ' | 'サ' | 'タ' | 'ナ' | 'ハ' | 'マ' | 'ヤ' | 'ラ' | 'ワ' | 'ガ' | 'ザ' | 'ダ'
+        | 'バ' | 'パ' => Some('ア'),
+        'イ' | 'キ' | 'シ' | 'チ' | 'ニ' | 'ヒ' | 'ミ' | 'リ' | 'ギ' | 'ジ' | 'ヂ' | 'ビ' | 'ピ' => {
+            Some('イ')
+        }
+        'ウ' | 'ク' | 'ス' | 'ツ' | 'ヌ' | 'フ' | 'ム' | 'ユ' | 'ル' | 'グ' | 'ズ' | 'ヅ' | 'ブ'
+        | 'プ' => Some('ウ'),
+        'エ' | 'ケ' | 'セ' | 'テ' | 'ネ' | 'ヘ' | 'メ' | 'レ' | 'ゲ' | 'ゼ' | 'デ' | 'ベ' | 'ペ' => {
+            Some('エ')
+        }
+        'オ' | 'コ' | 'ソ' | 'ト' | 'ノ' | 'ホ' | 'モ' | 'ヨ' | 'ロ' | 'ヲ' | 'ゴ' | 'ゾ' | 'ド'
+        | 'ボ' | 'ポ' => Some('オ'),
+        _ => None,
+    }
+}
+
+/// Fold a chōon mark (`ー`) — and the equally common お段+`ウ` long-vowel
+/// spelling it's interchangeable with, as in `オウ` / `オー` or `トウキョウ` /
+/// `トーキョー` — into a literal repetition of the vowel it extends, so both
+/// spellings of the same long vowel compare equal.
+fn expand_long_vowels(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut prev_vowel: Option<char> = None;
+    for ch in text.chars() {
+        let is_long_vowel_mark = ch == 'ー' || (ch == 'ウ' && prev_vowel == Some('オ'));
+        if is_long_vowel_mark {
+            if let Some(vowel) = prev_vowel {
+                result.push(vowel);
+                continue;
+            }
+        }
+        result.push(ch);
+        prev_vowel = katakana_vowel_column(ch);
+    }
+    result
+}
+
+/// Fold small kana (`ァィゥェォッャュョヮ`) to their large counterparts, so a
+/// reading that spells out a mora the user's query abbreviated (or vice
+/// versa) still compares equal.
+fn fold_small_kana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'ァ' => 'ア',
+            'ィ' => 'イ',
+            'ゥ' => 'ウ',
+            'ェ' => 'エ',
+            'ォ' => 'オ',
+            'ッ' => 'ツ',
+            'ャ' => 'ヤ',
+            'ュ' => 'ユ',
+            'ョ' => 'ヨ',
+            'ヮ' => 'ワ',
+            other => other,
+        })
+        .collect()
+}
+
+/// Strip dakuten/handakuten (rendaku voicing), e.g. `ガ`/`ザ`/`パ` all fold to
+/// their unvoiced base, by decomposing to NFD (which splits a precomposed
+/// voiced katakana into its base character plus a combining voice-mark) and
+/// dropping the combining marks.
+fn strip_dakuten(text: &str) -> String {
+    text.nfd()
+        .filter(|&c| c != '\u{3099}' && c != '\u{309A}')
+        .collect()
+}
+
+/// Fold the Japanese reading-spelling variation [`enumerate_reading_candidates_fuzzy`]
+/// tolerates for free (zero edit-distance cost), on top of whatever
+/// [`normalize_for_matching`] already did: long-vowel spelling, small/large
+/// kana, and dakuten/handakuten. Applied identically to the query and to
+/// every node's match variants, so genuine typos beyond this still cost
+/// edit distance while this spelling variation doesn't.
+fn normalize_for_phonological_matching(text: &str) -> String {
+    fold_phonological_variation(&normalize_for_matching(text))
+}
+
+fn fold_phonological_variation(text: &str) -> String {
+    expand_long_vowels(&fold_small_kana(&strip_dakuten(text)))
+}
+
+/// Which script a [`enumerate_reading_candidates_with_input_kind`] query is
+/// written in, so it knows whether (and how) to convert it to katakana
+/// before lattice matching.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum InputKind {
+    /// Already katakana; passed through [`normalize_for_matching`] as-is.
+    Katakana,
+    /// Hiragana; folded to katakana by [`normalize_for_matching`] as-is.
+    Hiragana,
+    /// Romaji (Hepburn-ish), converted to katakana by [`romaji_to_katakana`].
+    Romaji,
+    /// Detect the script from the query's first kana-or-ASCII-letter
+    /// character via [`detect_input_kind`] and dispatch accordingly.
+    Auto,
+}
+
+/// The surface normalization a [`ReadingCandidateFilter::require_surface_style`]
+/// constraint demands, as already exercised implicitly by readings that
+/// match a kanji, hiragana, or numeric surface.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SurfaceStyle {
+    Kanji,
+    Hiragana,
+    Katakana,
+    /// ASCII or full-width digits.
+    Numeric,
+}
+
+/// Whether every character of `surface` belongs to `style`; an empty surface
+/// matches no style.
+fn surface_matches_style(surface: &str, style: SurfaceStyle) -> bool {
+    if surface.is_empty() {
+        return false;
+    }
+    surface.chars().all(|c| {
+        let code = c as u32;
+        match style {
+            SurfaceStyle::Kanji => (0x4e00..=0x9fff).contains(&code) || (0x3400..=0x4dbf).contains(&code),
+            SurfaceStyle::Hiragana => (0x3041..=0x309f).contains(&code),
+            SurfaceStyle::Katakana => (0x30a0..=0x30ff).contains(&code),
+            SurfaceStyle::Numeric => c.is_ascii_digit() || (0xff10..=0xff19).contains(&code),
+        }
+    })
+}
+
+/// Guess a query's script from its first character that's either kana or an
+/// ASCII letter, analogous to the language-aware dispatch tokenizers use to
+/// pick a script-specific analyzer: a lone digit or punctuation run carries
+/// no script signal, so callers relying on `Auto` for those should pass an
+/// explicit [`InputKind`] instead.
+fn detect_input_kind(text: &str) -> InputKind {
+    for ch in text.chars() {
+        let code = ch as u32;
+        if (0x30a0..=0x30ff).contains(&code) {
+            return InputKind::Katakana;
+        }
+        if (0x3041..=0x309f).contains(&code) {
+            return InputKind::Hiragana;
+        }
+        if ch.is_ascii_alphabetic() {
+            return InputKind::Romaji;
+        }
+    }
+    InputKind::Katakana
+}
+
+/// Convert a macron (`āīūēō`) to its plain vowel doubled, so the long-vowel
+/// handling in [`romaji_to_katakana`] only has to deal with one spelling of
+/// a long vowel (`ou`/`oo`-style digraphs) rather than two. Assumes `text` is
+/// already lowercased, as [`romaji_to_katakana`] does before calling this.
+fn expand_macrons(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            'ā' => ['a', 'a'],
+            'ī' => ['i', 'i'],
+            'ū' => ['u', 'u'],
+            'ē' => ['e', 'e'],
+            'ō' => ['o', 'o'],
+            other => [other, '\0'],
+        })
+        .filter(|&c| c != '\0')
+        .collect()
+}
+
+fn romaji_vowel(ch: char) -> Option<char> {
+    match ch {
+        'a' => Some('a'),
+        'i' => Some('i'),
+        'u' => Some('u'),
+        'e' => Some('e'),
+        'o' => Some('o'),
+        _ => None,
+    }
+}
+
+/// Match the longest known romaji syllable (digraphs like `kya` first, then
+/// plain consonant+vowel, then a lone vowel) starting at `text`, returning
+/// its katakana spelling and how many bytes of `text` it consumed. `text` is
+/// assumed to already be lowercased ASCII.
+fn match_romaji_syllable(text: &str) -> Option<(&'static str, usize)> {
+    const SYLLABLES: &[(&str, &str)] = &[
+        // digraphs (consonant + small-y + vowel), longest first so e.g.
+        // "sha" doesn't get matched as "sh" + stray "a".
+        ("kya", "キャ"), ("kyu", "キュ"), ("kyo", "キョ"),
+        ("sha", "シャ"), ("shu", "シュ"), ("sho", "ショ"),
+        ("sya", "シャ"), ("syu", "シュ"), ("syo", "ショ"),
+        ("cha", "チャ"), ("chu", "チュ"), ("cho", "チョ"),
+        ("tya", "チャ"), ("tyu", "チュ"), ("tyo", "チョ"),
+        ("nya", "ニャ"), ("nyu", "ニュ"), ("nyo", "ニョ"),
+        ("hya", "ヒャ"), ("hyu", "ヒュ"), ("hyo", "ヒョ"),
+        ("mya", "ミャ"), ("myu", "ミュ"), ("myo", "ミョ"),
+        ("rya", "リャ"), ("ryu", "リュ"), ("ryo", "リョ"),
+        ("gya", "ギャ"), ("gyu", "ギュ"), ("gyo", "ギョ"),
+        ("zya", "ジャ"), ("zyu", "ジュ"), ("zyo", "ジョ"),
+        ("jya", "ジャ"), ("jyu", "ジュ"), ("jyo", "ジョ"),
+        ("bya", "ビャ"), ("byu", "ビュ"), ("byo", "ビョ"),
+        ("pya", "ピャ"), ("pyu", "ピュ"), ("pyo", "ピョ"),
+        ("dya", "ヂャ"), ("dyu", "ヂュ"), ("dyo", "ヂョ"),
+        // plain consonant + vowel.
+        ("ka", "カ"), ("ki", "キ"), ("ku", "ク"), ("ke", "ケ"), ("ko", "コ"),
+        ("sa", "サ"), ("shi", "シ"), ("si", "シ"), ("su", "ス"), ("se", "セ"), ("so", "ソ"),
+        ("ta", "タ"), ("chi", "チ"), ("ti", "チ"), ("tsu", "ツ"), ("tu", "ツ"), ("te", "テ"), ("to", "ト"),
+        ("na", "ナ"), ("ni", "ニ"), ("nu", "ヌ"), ("ne", "ネ"), ("no", "ノ"),
+        ("ha", "ハ"), ("hi", "ヒ"), ("fu", "フ"), ("hu", "フ"), ("he", "ヘ"), ("ho", "ホ"),
+        ("ma", "マ"), ("mi", "ミ"), ("mu", "ム"), ("me", "メ"), ("mo", "モ"),
+        ("ya", "ヤ"), ("yu", "ユ"), ("yo", "ヨ"),
+        ("ra", "ラ"), ("ri", "リ"), ("ru", "ル"), ("re", "レ"), ("ro", "ロ"),
+        ("wa", "ワ"), ("wo", "ヲ"), ("wi", "ウィ"), ("we", "ウェ"),
+        ("ga", "ガ"), ("gi", "ギ"), ("gu", "グ"), ("ge", "ゲ"), ("go", "ゴ"),
+        ("za", "ザ"), ("ji", "ジ"), ("zi", "ジ"), ("zu", "ズ"), ("ze", "ゼ"), ("zo", "ゾ"),
+        ("da", "ダ"), ("di", "ヂ"), ("du", "ヅ"), ("de", "デ"), ("do", "ド"),
+        ("ba", "バ"), ("bi", "ビ"), ("bu", "ブ"), ("be", "ベ"), ("bo", "ボ"),
+        ("pa", "パ"), ("pi", "ピ"), ("pu", "プ"), ("pe", "ペ"), ("po", "ポ"),
+        ("vu", "ヴ"),
+        // lone vowels.
+        ("a", "ア"), ("i", "イ"), ("u", "ウ"), ("e", "エ"), ("o", "オ"),
+    ];
+    for &(romaji, kana) in SYLLABLES {
+        if text.starts_with(romaji) {
+            return Some((kana, romaji.len()));
+        }
+    }
+    None
+}
+
+/// Convert romaji (Hepburn-ish, case-insensitive) to katakana: `n`/`nn`,
+/// doubled consonants as the sokuon (`ッ`), and macron or `ou`/`oo`-style
+/// long vowels are all recognized, but this isn't an exhaustive IME-grade
+/// converter — unrecognized runs of ASCII letters are passed through
+/// unchanged so a caller can see what didn't convert rather than have it
+/// silently dropped.
+fn romaji_to_katakana(text: &str) -> String {
+    let expanded = expand_macrons(&text.to_lowercase());
+    let mut result = String::with_capacity(expanded.len());
+    let mut rest = expanded.as_str();
+    while !rest.is_empty() {
+        let ch = rest.chars().next().unwrap();
+        if !ch.is_ascii_alphabetic() {
+            result.push(ch);
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        }
+        // Sokuon: a consonant immediately followed by itself (not "nn",
+        // handled separately below) geminates the next syllable.
+        if ch != 'n' && romaji_vowel(ch).is_none() && rest.as_bytes().get(1) == Some(&(ch as u8)) {
+            result.push('ッ');
+            rest = &rest[1..];
+            continue;
+        }
+        if ch == 'n' {
+            let next = rest[1..].chars().next();
+            let followed_by_vowel_or_y = next.map_or(false, |c| romaji_vowel(c).is_some() || c == 'y');
+            if !followed_by_vowel_or_y {
+                // Consume only this one "n" as ン: a doubled "nn" before a
+                // vowel (e.g. "honnou") isn't a geminate consonant here, it's
+                // ん followed by a な-row syllable that also starts with
+                // "n" — the second "n" is left for the next syllable match.
+                result.push('ン');
+                rest = &rest[1..];
+                continue;
+            }
+        }
+        if let Some((kana, consumed)) = match_romaji_syllable(rest) {
+            let syllable_vowel = rest[..consumed].chars().last().and_then(romaji_vowel);
+            result.push_str(kana);
+            rest = &rest[consumed..];
+            // Long vowel: the syllable just emitted is immediately followed
+            // by its own vowel letter again ("oo"), or by "u" after an
+            // "o"-ending syllable ("ou", e.g. "toukyou" -> "トーキョー").
+            if let Some(vowel) = syllable_vowel {
+                let next = rest.chars().next();
+                if next == Some(vowel) || (vowel == 'o' && next == Some('u')) {
+                    result.push('ー');
+                    rest = &rest[1..];
+                }
+            }
+            continue;
+        }
+        // Unrecognized letter run: pass through unchanged rather than drop.
+        result.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    result
+}
+
+/// A normalized form a node can be matched against, and the extra cost
+/// incurred by accepting a match through it. Literal (surface/reading)
+/// variants carry no penalty; variants pulled in from synonym group members
+/// carry `synonym_penalty` so literal matches still rank first.
+#[derive(Clone, Debug)]
+struct MatchVariant {
+    text: String,
+    penalty: i32,
+}
+
+/// An index from a synonym group id to the word ids that belong to it, used
+/// to fold a token's synonyms into its reading-search match variants.
+/// [`WordInfo::synonym_group_ids`] maps a word to its groups, but
+/// [`LexiconSet`] has no group-to-members lookup, so this index is built
+/// separately (with [`from_lexicon`](Self::from_lexicon) over a set of word
+/// ids, or with [`insert`](Self::insert)) and reused across queries.
+#[derive(Default, Clone)]
+pub struct SynonymGroups {
+    groups: HashMap<u32, Vec<WordId>>,
+}
+
+impl SynonymGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `word_id` as a member of `group_id`.
+    pub fn insert(&mut self, group_id: u32, word_id: WordId) {
+        self.groups.entry(group_id).or_default().push(word_id);
+    }
+
+    fn members(&self, group_id: u32) -> &[WordId] {
+        self.groups
+            .get(&group_id)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Build group membership from the dictionary itself: for every id in
+    /// `word_ids`, look its `WordInfo` up through `lexicon` and register it
+    /// as a member of each synonym group it declares via
+    /// [`WordInfo::synonym_group_ids`]. This is the builder callers are
+    /// expected to use in practice — `insert` remains for tests and for
+    /// composing extra group membership the dictionary doesn't know about.
+    pub fn from_lexicon(
+        word_ids: impl IntoIterator<Item = WordId>,
+        lexicon: &LexiconSet,
+        subset: InfoSubset,
+    ) -> SudachiResult<Self> {
+        let mut groups = Self::new();
+        for word_id in word_ids {
+            let info = lexicon.get_word_info_subset(word_id, subset)?;
+            for &group_id in info.synonym_group_ids().iter() {
+                groups.insert(group_id, word_id);
+            }
+        }
+        Ok(groups)
+    }
+}
+
+/// Cross-call cache for [`enumerate_reading_candidates_with_options`] and
+/// friends, for interactive/IME callers that re-query the same (or a
+/// growing-prefix) reading against the same lattice repeatedly.
+///
+/// The match-variant half is keyed only by word id, so it stays valid across
+/// lattices built from different input and is never invalidated; rebuilding
+/// `nodes_by_begin`/`metas_by_end` still happens per call (that part is
+/// genuinely lattice-shaped), but the per-word `normalize_for_matching` /
+/// `build_match_variants` work it depends on is only ever done once per
+/// word. The minimum-additional-cost half mirrors the searcher's internal
+/// memo table but survives across calls; call
+/// [`invalidate_for_new_lattice`](Self::invalidate_for_new_lattice) whenever
+/// the lattice being queried changes, since `SearchState` only encodes
+/// position within *a* lattice, not which one. It's also dropped
+/// automatically whenever the query text, `max_typo`, `typo_penalty`,
+/// `is_prefix` or the split-bridge settings change, since `SearchState`
+/// likewise doesn't encode which query it was computed for — see
+/// [`CostCacheQuery`]. The synonym, phonological-folding and candidate-filter
+/// options are not part of that check: changing one of them between calls
+/// against the same lattice keeps the existing cost entries.
+#[derive(Default)]
+pub struct ReadingSearchCache {
+    variants: RefCell<HashMap<u32, Vec<MatchVariant>>>,
+    cost_cache: RefCell<HashMap<SearchState, Option<i32>>>,
+    cost_cache_query: RefCell<Option<CostCacheQuery>>,
+}
+
+/// The part of a search call that `min_additional_cost_from_state` results
+/// actually depend on beyond a bare `SearchState`: the query text itself
+/// (the automaton state only records *position* within it) plus the options
+/// the searcher itself is given that change how remaining cost is computed.
+/// Options that change a node's match variants (synonyms, phonological
+/// folding, candidate filter) are not recorded here, so two calls that
+/// differ only in those share `ReadingSearchCache::cost_cache` entries.
+#[derive(Clone, Eq, PartialEq)]
+struct CostCacheQuery {
+    query: Vec<char>,
+    max_typo: u32,
+    typo_penalty: i32,
+    is_prefix: bool,
+    allow_bridge: bool,
+    bridge_penalty: i32,
+}
+
+impl ReadingSearchCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get_variants(&self, word_id: WordId) -> Option<Vec<MatchVariant>> {
+        self.variants.borrow().get(&word_id.as_raw()).cloned()
+    }
+
+    fn put_variants(&self, word_id: WordId, variants: Vec<MatchVariant>) {
+        self.variants
+            .borrow_mut()
+            .insert(word_id.as_raw(), variants);
+    }
+
+    /// Drop the per-lattice minimum-additional-cost memo, e.g. before
+    /// re-querying against a freshly retokenized lattice. The word-level
+    /// variant cache is unaffected and keeps paying off across lattices.
+    pub fn invalidate_for_new_lattice(&self) {
+        self.cost_cache.borrow_mut().clear();
+        *self.cost_cache_query.borrow_mut() = None;
+    }
+
+    /// Drop the minimum-additional-cost memo if it was last populated for a
+    /// different query (or a different `max_typo`/`typo_penalty`/`is_prefix`/
+    /// bridge setting) than `query`, so two distinct queries against the same
+    /// unchanged lattice can't read each other's cost entries through a
+    /// `SearchState` that happens to collide. A no-op when `query` matches
+    /// what's already cached (the same query repeated); a growing prefix is
+    /// a different query on every keystroke, so it clears the memo.
+    fn invalidate_for_new_query(&self, query: &CostCacheQuery) {
+        let mut last = self.cost_cache_query.borrow_mut();
+        if last.as_ref() != Some(query) {
+            self.cost_cache.borrow_mut().clear();
+            *last = Some(query.clone());
+        }
+    }
+}
+
+fn build_match_variants(word_info: &WordInfo) -> (Vec<MatchVariant>, HashSet<String>) {
     let mut variants = Vec::new();
     let mut seen = HashSet::new();
 
@@ -122,11 +715,50 @@ fn build_match_variants(word_info: &WordInfo) -> Vec<String> {
             continue;
         }
         if seen.insert(normalized.clone()) {
-            variants.push(normalized);
+            variants.push(MatchVariant {
+                text: normalized,
+                penalty: 0,
+            });
         }
     }
 
-    variants
+    (variants, seen)
+}
+
+/// Pull the surface/reading of every other member of `word_info`'s synonym
+/// groups into `variants`, so a reading query can match a token via a
+/// synonym it's known to share, the way MeiliSearch folds synonyms into its
+/// query derivations.
+fn extend_match_variants_with_synonyms(
+    variants: &mut Vec<MatchVariant>,
+    seen: &mut HashSet<String>,
+    word_info: &WordInfo,
+    lexicon: &LexiconSet,
+    subset: InfoSubset,
+    synonyms: &SynonymGroups,
+    synonym_penalty: i32,
+) -> SudachiResult<()> {
+    for &group_id in word_info.synonym_group_ids().iter() {
+        for &member in synonyms.members(group_id) {
+            let member_info = lexicon.get_word_info_subset(member, subset)?;
+            for raw in [member_info.reading_form(), member_info.surface()] {
+                if raw.is_empty() {
+                    continue;
+                }
+                let normalized = normalize_for_matching(raw);
+                if normalized.is_empty() {
+                    continue;
+                }
+                if seen.insert(normalized.clone()) {
+                    variants.push(MatchVariant {
+                        text: normalized,
+                        penalty: synonym_penalty,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
 }
 
 fn make_word_info(
@@ -150,46 +782,88 @@ fn make_word_info(
 
 struct Searcher<'a> {
     conn: &'a ConnectionMatrix<'a>,
-    reading: &'a [u8],
+    input: &'a InputBuffer,
+    query: Vec<char>,
+    max_typo: u32,
+    typo_penalty: i32,
+    is_prefix: bool,
+    allow_bridge: bool,
+    bridge_penalty: i32,
     end_boundary: usize,
     max_results: usize,
     min_tokens: usize,
+    max_tokens: usize,
+    exact_kbest: bool,
     nodes_by_begin: &'a [Vec<NodeRef>],
     metas_by_end: &'a [Vec<NodeMeta>],
     path: Vec<NodeRef>,
     results: Vec<ReadingCandidatePath>,
-    min_cost_cache: HashMap<SearchState, Option<i32>>,
+    local_cost_cache: HashMap<SearchState, Option<i32>>,
+    shared_cache: Option<&'a ReadingSearchCache>,
 }
 
 impl<'a> Searcher<'a> {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         conn: &'a ConnectionMatrix<'a>,
+        input: &'a InputBuffer,
         reading: &'a str,
+        max_typo: usize,
+        typo_penalty: i32,
+        is_prefix: bool,
+        allow_bridge: bool,
+        bridge_penalty: i32,
         end_boundary: usize,
         max_results: usize,
         min_tokens: usize,
+        max_tokens: usize,
+        exact_kbest: bool,
         nodes_by_begin: &'a [Vec<NodeRef>],
         metas_by_end: &'a [Vec<NodeMeta>],
+        shared_cache: Option<&'a ReadingSearchCache>,
     ) -> Self {
+        let query: Vec<char> = reading.chars().collect();
+        if let Some(shared) = shared_cache {
+            shared.invalidate_for_new_query(&CostCacheQuery {
+                query: query.clone(),
+                max_typo: max_typo as u32,
+                typo_penalty,
+                is_prefix,
+                allow_bridge,
+                bridge_penalty,
+            });
+        }
         Self {
             conn,
-            reading: reading.as_bytes(),
+            input,
+            query,
+            max_typo: max_typo as u32,
+            typo_penalty,
+            is_prefix,
+            allow_bridge,
+            bridge_penalty,
             end_boundary,
             max_results,
             min_tokens,
+            max_tokens,
+            exact_kbest,
             nodes_by_begin,
             metas_by_end,
             path: Vec::new(),
             results: Vec::new(),
-            min_cost_cache: HashMap::new(),
+            local_cost_cache: HashMap::new(),
+            shared_cache,
         }
     }
 
     fn run(mut self) -> Vec<ReadingCandidatePath> {
+        if self.exact_kbest {
+            return self.run_exact_kbest();
+        }
         let start = SearchState {
             boundary: 0,
             prev_right_id: 0,
-            reading_offset: 0,
+            automaton: vec![(0, 0)],
         };
         self.dfs(start, 0);
         self.results.sort_by(|a, b| a.total_cost.cmp(&b.total_cost));
@@ -199,6 +873,109 @@ impl<'a> Searcher<'a> {
         self.results
     }
 
+    /// Exact k-shortest-path enumeration via best-first search over the
+    /// implicit `SearchState` graph, following the forward-Viterbi +
+    /// backward-A* scheme used by Viterbi tokenizers like Vibrato: each queue
+    /// entry is a partial path from BOS together with the exact minimum cost
+    /// still needed to complete it
+    /// ([`min_additional_cost_from_state`](Self::min_additional_cost_from_state),
+    /// which doubles as the admissible-and-exact A* heuristic here), and
+    /// entries are popped in ascending order of that estimate. An accepting
+    /// path is queued again as a finished entry keyed by its exact
+    /// `total_cost`, so the first `max_results` finished entries popped are
+    /// provably the globally cheapest paths, in ascending `total_cost` order,
+    /// with no reliance on branch-and-bound pruning or a final sort.
+    fn run_exact_kbest(&mut self) -> Vec<ReadingCandidatePath> {
+        struct Entry {
+            est_total: i32,
+            base_cost: i32,
+            path: Vec<NodeRef>,
+            state: SearchState,
+            /// Edit distance of an accepted path whose `base_cost` is final.
+            finished: Option<u32>,
+        }
+
+        impl PartialEq for Entry {
+            fn eq(&self, other: &Self) -> bool {
+                self.est_total == other.est_total
+            }
+        }
+        impl Eq for Entry {}
+        impl PartialOrd for Entry {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Entry {
+            fn cmp(&self, other: &Self) -> Ordering {
+                // Reversed so `BinaryHeap`, a max-heap, pops the cheapest entry first.
+                other.est_total.cmp(&self.est_total)
+            }
+        }
+
+        let start = SearchState {
+            boundary: 0,
+            prev_right_id: 0,
+            automaton: vec![(0, 0)],
+        };
+        let mut heap = BinaryHeap::new();
+        if let Some(est_total) = self.min_additional_cost_from_state(start.clone()) {
+            heap.push(Entry {
+                est_total,
+                base_cost: 0,
+                path: Vec::new(),
+                state: start,
+                finished: None,
+            });
+        }
+
+        let mut results = Vec::new();
+        while let Some(entry) = heap.pop() {
+            if results.len() >= self.max_results {
+                break;
+            }
+
+            if let Some(errors) = entry.finished {
+                results.push(self.candidate_from_path(&entry.path, entry.base_cost, errors));
+                continue;
+            }
+
+            if let Some(acceptance) = self.acceptance_cost(&entry.state) {
+                if entry.path.len() >= self.min_tokens {
+                    let total_cost = entry.base_cost + acceptance;
+                    let errors = self.accepted_edit_distance(&entry.state).unwrap_or(0);
+                    heap.push(Entry {
+                        est_total: total_cost,
+                        base_cost: total_cost,
+                        path: entry.path.clone(),
+                        state: entry.state.clone(),
+                        finished: Some(errors),
+                    });
+                }
+            }
+
+            if entry.state.boundary == self.end_boundary || entry.path.len() >= self.max_tokens {
+                continue;
+            }
+
+            for (step_cost, nodes, next_state) in self.expand_transitions(&entry.state) {
+                let Some(rem) = self.min_additional_cost_from_state(next_state.clone()) else {
+                    continue;
+                };
+                let mut path = entry.path.clone();
+                path.extend(nodes);
+                heap.push(Entry {
+                    est_total: entry.base_cost + step_cost + rem,
+                    base_cost: entry.base_cost + step_cost,
+                    path,
+                    state: next_state,
+                    finished: None,
+                });
+            }
+        }
+        results
+    }
+
     fn worst_kept_cost(&self) -> Option<i32> {
         if self.results.len() < self.max_results {
             None
@@ -207,13 +984,49 @@ impl<'a> Searcher<'a> {
         }
     }
 
-    fn record_result(&mut self, total_cost: i32) {
-        let mut tokens = Vec::with_capacity(self.path.len());
-        for node_ref in &self.path {
+    fn candidate_from_path(
+        &self,
+        path: &[NodeRef],
+        total_cost: i32,
+        reading_edit_distance: u32,
+    ) -> ReadingCandidatePath {
+        let mut tokens = Vec::with_capacity(path.len());
+        for node_ref in path {
             let meta = &self.metas_by_end[node_ref.end][node_ref.index];
             tokens.push(meta.as_candidate_token());
         }
-        let candidate = ReadingCandidatePath { total_cost, tokens };
+        let covered_surface_end = tokens.last().map_or(0, |t| t.end);
+        let remaining_surface = if covered_surface_end < self.end_boundary {
+            self.input
+                .curr_slice_c(covered_surface_end..self.end_boundary)
+                .to_owned()
+        } else {
+            String::new()
+        };
+        ReadingCandidatePath {
+            total_cost,
+            // Filled in by `assign_softmax_probabilities` once the full
+            // result set is known; a single path's probability isn't
+            // meaningful alone.
+            probability: 0.0,
+            covered_reading_len: self.query.len(),
+            remaining_surface,
+            reading_edit_distance,
+            tokens,
+        }
+    }
+
+    /// Raw accumulated edit distance of an accepting `state`, i.e. the same
+    /// quantity [`acceptance_cost`](Self::acceptance_cost) folds into a
+    /// `typo_penalty`-scaled cost, surfaced separately for
+    /// [`ReadingCandidatePath::reading_edit_distance`].
+    fn accepted_edit_distance(&self, state: &SearchState) -> Option<u32> {
+        automaton_accepts(&state.automaton, self.query.len() as u32)
+    }
+
+    fn record_result(&mut self, total_cost: i32, reading_edit_distance: u32) {
+        let candidate =
+            self.candidate_from_path(&self.path.clone(), total_cost, reading_edit_distance);
         if self.results.len() < self.max_results {
             self.results.push(candidate);
             return;
@@ -231,60 +1044,158 @@ impl<'a> Searcher<'a> {
         }
     }
 
-    fn min_additional_cost_from_state(&mut self, state: SearchState) -> Option<i32> {
-        if let Some(cached) = self.min_cost_cache.get(&state) {
-            return *cached;
+    /// Cost of accepting at `state`'s boundary, if it is acceptable at all.
+    /// At the lattice's true end boundary this is the BOS/EOS connection
+    /// cost; with `is_prefix` set, any boundary at which the automaton has
+    /// fully consumed the query is also acceptable (no EOS connection is
+    /// actually made there, since the path may continue on a later keystroke).
+    /// Either way, the typo penalty for accrued errors is folded in.
+    fn acceptance_cost(&self, state: &SearchState) -> Option<i32> {
+        let errors = automaton_accepts(&state.automaton, self.query.len() as u32)?;
+        let penalty = errors as i32 * self.typo_penalty;
+        if state.boundary == self.end_boundary {
+            Some(self.conn.cost(state.prev_right_id, 0) as i32 + penalty)
+        } else if self.is_prefix {
+            Some(penalty)
+        } else {
+            None
         }
+    }
 
-        let result = if state.boundary == self.end_boundary {
-            if state.reading_offset == self.reading.len() {
-                Some(self.conn.cost(state.prev_right_id, 0) as i32)
-            } else {
-                None
-            }
-        } else {
-            let node_refs = self.nodes_by_begin[state.boundary].clone();
-            let mut best: Option<i32> = None;
-
-            for node_ref in node_refs {
-                let meta = &self.metas_by_end[node_ref.end][node_ref.index];
-                let step_cost = self.conn.cost(state.prev_right_id, meta.node.left_id()) as i32
-                    + meta.node.cost() as i32;
-                for token_reading in &meta.match_variants {
-                    let token_reading = token_reading.as_bytes();
-                    if token_reading.is_empty() {
-                        continue;
-                    }
-                    if state.reading_offset + token_reading.len() > self.reading.len() {
-                        continue;
-                    }
-                    if !self.reading[state.reading_offset..].starts_with(token_reading) {
-                        continue;
-                    }
+    /// Enumerate every way to leave `state`: one lattice node at a time,
+    /// matched against a single node's variants, plus (when bridging is
+    /// enabled) a two-node lookahead that matches the *concatenation* of a
+    /// node and its immediate successor's variants against the remaining
+    /// query in one step. This lets a reading that splits unevenly across a
+    /// morpheme boundary (e.g. rendaku/gemination at the join) still be
+    /// found, and lets a tight `max_tokens` budget — which only ever lets
+    /// exploration look one node further per step — still reach a match
+    /// that needs both nodes.
+    fn expand_transitions(&self, state: &SearchState) -> Vec<(i32, Vec<NodeRef>, SearchState)> {
+        let mut transitions = Vec::new();
+
+        for &node_ref in &self.nodes_by_begin[state.boundary] {
+            let meta = &self.metas_by_end[node_ref.end][node_ref.index];
+            let node_cost = self.conn.cost(state.prev_right_id, meta.node.left_id()) as i32
+                + meta.node.cost() as i32;
 
+            for variant in &meta.match_variants {
+                if let Some(automaton) = automaton_consume_variant(
+                    &state.automaton,
+                    &variant.text,
+                    &self.query,
+                    self.max_typo,
+                    self.is_prefix,
+                ) {
                     let next_state = SearchState {
                         boundary: meta.node.end(),
                         prev_right_id: meta.node.right_id(),
-                        reading_offset: state.reading_offset + token_reading.len(),
+                        automaton,
                     };
-                    if let Some(rem) = self.min_additional_cost_from_state(next_state) {
-                        let candidate = step_cost + rem;
-                        best = match best {
-                            None => Some(candidate),
-                            Some(cur) => Some(cur.min(candidate)),
+                    transitions.push((node_cost + variant.penalty, vec![node_ref], next_state));
+                }
+            }
+
+            // Always attempt the two-node lookahead alongside any single-node
+            // match rather than only when every single-node variant failed:
+            // a node's own match succeeding doesn't mean it can still be
+            // *reached* downstream of a tight `max_tokens` budget, since that
+            // budget counts lattice nodes pushed onto the path so far, not
+            // how many bridgeable "steps" were taken to get there. Gating
+            // this on the single-node outcome left `allow_bridge` unable to
+            // do anything for exactly the case it exists for: a reading that
+            // only resolves by joining two nodes' variants, explored within
+            // one step's budget. The extra candidates this generates when a
+            // single-node match also exists are always at least
+            // `bridge_penalty` costlier for the same node pair, so they
+            // never outrank it.
+            if !self.allow_bridge {
+                continue;
+            }
+
+            for &node_ref_b in &self.nodes_by_begin[meta.node.end()] {
+                let meta_b = &self.metas_by_end[node_ref_b.end][node_ref_b.index];
+                let cross_cost = self.conn.cost(meta.node.right_id(), meta_b.node.left_id()) as i32
+                    + meta_b.node.cost() as i32;
+                for variant_a in &meta.match_variants {
+                    for variant_b in &meta_b.match_variants {
+                        let mut joined =
+                            String::with_capacity(variant_a.text.len() + variant_b.text.len());
+                        joined.push_str(&variant_a.text);
+                        joined.push_str(&variant_b.text);
+                        let Some(automaton) = automaton_consume_variant(
+                            &state.automaton,
+                            &joined,
+                            &self.query,
+                            self.max_typo,
+                            self.is_prefix,
+                        ) else {
+                            continue;
                         };
+                        let next_state = SearchState {
+                            boundary: meta_b.node.end(),
+                            prev_right_id: meta_b.node.right_id(),
+                            automaton,
+                        };
+                        let cost = node_cost
+                            + variant_a.penalty
+                            + cross_cost
+                            + variant_b.penalty
+                            + self.bridge_penalty;
+                        transitions.push((cost, vec![node_ref, node_ref_b], next_state));
                     }
                 }
             }
-            best
-        };
+        }
+
+        transitions
+    }
+
+    /// Look up a previously computed minimum-additional-cost, consulting the
+    /// cross-call [`ReadingSearchCache`] first when one was supplied so a
+    /// repeated/incremental query against the same lattice can skip work this
+    /// searcher hasn't even started yet; falls back to the per-call memo.
+    fn cache_get(&self, state: &SearchState) -> Option<Option<i32>> {
+        if let Some(shared) = self.shared_cache {
+            if let Some(cached) = shared.cost_cache.borrow().get(state) {
+                return Some(*cached);
+            }
+        }
+        self.local_cost_cache.get(state).copied()
+    }
+
+    fn cache_insert(&mut self, state: SearchState, value: Option<i32>) {
+        if let Some(shared) = self.shared_cache {
+            shared.cost_cache.borrow_mut().insert(state.clone(), value);
+        }
+        self.local_cost_cache.insert(state, value);
+    }
+
+    fn min_additional_cost_from_state(&mut self, state: SearchState) -> Option<i32> {
+        if let Some(cached) = self.cache_get(&state) {
+            return cached;
+        }
+
+        let mut best = self.acceptance_cost(&state);
+
+        if state.boundary != self.end_boundary {
+            for (step_cost, _nodes, next_state) in self.expand_transitions(&state) {
+                if let Some(rem) = self.min_additional_cost_from_state(next_state) {
+                    let candidate = step_cost + rem;
+                    best = match best {
+                        None => Some(candidate),
+                        Some(cur) => Some(cur.min(candidate)),
+                    };
+                }
+            }
+        }
 
-        self.min_cost_cache.insert(state, result);
-        result
+        self.cache_insert(state, best);
+        best
     }
 
     fn dfs(&mut self, state: SearchState, base_cost: i32) {
-        let Some(min_additional) = self.min_additional_cost_from_state(state) else {
+        let Some(min_additional) = self.min_additional_cost_from_state(state.clone()) else {
             return;
         };
 
@@ -294,45 +1205,22 @@ impl<'a> Searcher<'a> {
             }
         }
 
-        if state.boundary == self.end_boundary {
-            if state.reading_offset != self.reading.len() {
-                return;
-            }
-            if self.path.len() < self.min_tokens {
-                return;
+        if let Some(acceptance) = self.acceptance_cost(&state) {
+            if self.path.len() >= self.min_tokens {
+                let errors = self.accepted_edit_distance(&state).unwrap_or(0);
+                self.record_result(base_cost + acceptance, errors);
             }
-            let total_cost = base_cost + self.conn.cost(state.prev_right_id, 0) as i32;
-            self.record_result(total_cost);
-            return;
         }
 
-        let node_refs = self.nodes_by_begin[state.boundary].clone();
-        let mut transitions: Vec<(i32, i32, NodeRef, SearchState)> = Vec::new();
+        if state.boundary == self.end_boundary || self.path.len() >= self.max_tokens {
+            return;
+        }
 
-        for node_ref in node_refs {
-            let meta = &self.metas_by_end[node_ref.end][node_ref.index];
-            let step_cost = self.conn.cost(state.prev_right_id, meta.node.left_id()) as i32
-                + meta.node.cost() as i32;
-            for token_reading in &meta.match_variants {
-                let token_reading = token_reading.as_bytes();
-                if token_reading.is_empty() {
-                    continue;
-                }
-                if state.reading_offset + token_reading.len() > self.reading.len() {
-                    continue;
-                }
-                if !self.reading[state.reading_offset..].starts_with(token_reading) {
-                    continue;
-                }
-                let next_state = SearchState {
-                    boundary: meta.node.end(),
-                    prev_right_id: meta.node.right_id(),
-                    reading_offset: state.reading_offset + token_reading.len(),
-                };
-                if let Some(rem) = self.min_additional_cost_from_state(next_state) {
-                    let est_total = base_cost + step_cost + rem;
-                    transitions.push((est_total, step_cost, node_ref, next_state));
-                }
+        let mut transitions: Vec<(i32, i32, Vec<NodeRef>, SearchState)> = Vec::new();
+        for (step_cost, nodes, next_state) in self.expand_transitions(&state) {
+            if let Some(rem) = self.min_additional_cost_from_state(next_state.clone()) {
+                let est_total = base_cost + step_cost + rem;
+                transitions.push((est_total, step_cost, nodes, next_state));
             }
         }
 
@@ -344,19 +1232,197 @@ impl<'a> Searcher<'a> {
             }
         });
 
-        for (est_total, step_cost, node_ref, next_state) in transitions {
+        for (est_total, step_cost, nodes, next_state) in transitions {
             if let Some(worst_kept) = self.worst_kept_cost() {
                 if est_total > worst_kept {
                     continue;
                 }
             }
-            self.path.push(node_ref);
+            let pushed = nodes.len();
+            for node_ref in nodes {
+                self.path.push(node_ref);
+            }
             self.dfs(next_state, base_cost + step_cost);
-            self.path.pop();
+            for _ in 0..pushed {
+                self.path.pop();
+            }
+        }
+    }
+}
+
+/// Constrains which lattice nodes [`enumerate_reading_candidates_filtered`]
+/// may use to build a path, applied while the lattice is walked rather than
+/// after a path is already built — a node that fails the filter is simply
+/// never added to the search graph, so a pruned node can never contribute a
+/// path that gets thrown away later, and `max_results` returned paths are
+/// always that many *valid* ones rather than fewer after filtering.
+pub struct ReadingCandidateFilter<'a> {
+    /// Paths with fewer tokens than this are rejected.
+    pub min_tokens: usize,
+    /// Paths with more tokens than this are rejected; the search stops
+    /// expanding a path once it reaches this many tokens.
+    pub max_tokens: usize,
+    /// Each inner `Vec` is a POS prefix pattern, in the dictionary's POS
+    /// hierarchy order (e.g. `["名詞", "固有名詞", "地名"]` for place-name
+    /// readings); a token is allowed if its own POS tags start with at
+    /// least one of these patterns level-by-level. `None` allows every POS.
+    pub allowed_pos_prefixes: Option<Vec<Vec<String>>>,
+    /// Every token's surface must match this normalization style. `None`
+    /// allows any surface.
+    pub require_surface_style: Option<SurfaceStyle>,
+    /// Resolves a node's POS id to its POS tags; only consulted when
+    /// `allowed_pos_prefixes` is `Some`.
+    grammar: Option<&'a Grammar>,
+}
+
+impl<'a> ReadingCandidateFilter<'a> {
+    /// A filter with no constraints beyond the default `min_tokens` of `1`
+    /// (matching [`enumerate_reading_candidates`]'s own default).
+    /// `allowed_pos_prefixes` requires a `grammar`; set it with
+    /// [`ReadingCandidateFilter::with_pos_prefixes`].
+    pub fn new() -> Self {
+        Self {
+            min_tokens: 1,
+            max_tokens: usize::MAX,
+            allowed_pos_prefixes: None,
+            require_surface_style: None,
+            grammar: None,
+        }
+    }
+
+    /// Restrict candidates to tokens whose POS tags start with one of
+    /// `prefixes`, resolving each node's POS id against `grammar`.
+    pub fn with_pos_prefixes(mut self, grammar: &'a Grammar, prefixes: Vec<Vec<String>>) -> Self {
+        self.grammar = Some(grammar);
+        self.allowed_pos_prefixes = Some(prefixes);
+        self
+    }
+
+    fn node_allowed(&self, word_info: &WordInfo) -> bool {
+        if let Some(style) = self.require_surface_style {
+            if !surface_matches_style(word_info.surface(), style) {
+                return false;
+            }
+        }
+        if let Some(prefixes) = &self.allowed_pos_prefixes {
+            let pos = self
+                .grammar
+                .and_then(|g| g.pos_list.get(word_info.pos_id() as usize))
+                .map(Vec::as_slice)
+                .unwrap_or(&[]);
+            let matches = prefixes.iter().any(|prefix| {
+                prefix.len() <= pos.len() && prefix.iter().zip(pos).all(|(want, got)| want == got)
+            });
+            if !matches {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Default for ReadingCandidateFilter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tuning knobs for [`enumerate_reading_candidates_with_options`]. Grouped
+/// into a struct (rather than more positional arguments) once the matcher
+/// grew past typo-tolerance and prefix mode; `Default` reproduces the exact,
+/// full-reading, literal-only behavior of the original matcher.
+pub struct ReadingSearchOptions<'a> {
+    /// Maximum number of character-level edits (insert/delete/substitute)
+    /// tolerated between the query and a node's match variants.
+    pub max_typo: usize,
+    /// Cost added to a path's `total_cost` per absorbed edit.
+    pub typo_penalty: i32,
+    /// Accept paths whose reading is a prefix of the query, rather than
+    /// requiring the whole query to be covered, for IME-style callers.
+    pub is_prefix: bool,
+    /// Synonym group membership used to fold synonym surfaces/readings into
+    /// a node's match variants. `None` disables synonym expansion.
+    pub synonyms: Option<&'a SynonymGroups>,
+    /// Cost added to a path's `total_cost` for each token matched via a
+    /// synonym rather than its own surface/reading.
+    pub synonym_penalty: i32,
+    /// Allow a reading segment to be matched against the concatenation of
+    /// two consecutive lattice nodes when no single node starting there
+    /// matches on its own, so a reading that splits unevenly across a
+    /// morpheme boundary can still be found.
+    pub allow_split_bridge: bool,
+    /// Cost added to a path's `total_cost` for each two-node bridge used.
+    pub bridge_penalty: i32,
+    /// Use the exact k-shortest-path best-first enumerator instead of the
+    /// branch-and-bound DFS. Both return the same globally cheapest
+    /// `max_results` paths in ascending `total_cost` order; this trades the
+    /// DFS's lower constant factor for a search that never has to
+    /// over-explore before it can start pruning.
+    pub exact_kbest: bool,
+    /// Softmax temperature used to derive [`ReadingCandidatePath::probability`]
+    /// from the returned paths' `total_cost`s. Lower values sharpen the
+    /// distribution around the cheapest path; it is clamped above zero
+    /// internally, so a non-positive value still recovers a (numerically
+    /// safe) near-hard-argmax distribution rather than `NaN`.
+    pub temperature: f64,
+    /// Fold common Japanese reading-spelling variation — long vowels
+    /// (`ー` vs. spelling the vowel out, `オウ` vs. `オー`), small/large kana,
+    /// and dakuten/handakuten — into a shared canonical form before matching,
+    /// so this variation costs no edit distance at all, on top of whatever
+    /// `max_typo` budget is still available for actual typos. See
+    /// [`enumerate_reading_candidates_fuzzy`].
+    pub phonological_fuzzy: bool,
+    /// Constrain which nodes the search may use; pruned during traversal.
+    /// See [`enumerate_reading_candidates_filtered`].
+    pub candidate_filter: Option<&'a ReadingCandidateFilter<'a>>,
+}
+
+impl Default for ReadingSearchOptions<'_> {
+    fn default() -> Self {
+        Self {
+            max_typo: 0,
+            typo_penalty: DEFAULT_TYPO_PENALTY,
+            is_prefix: false,
+            synonyms: None,
+            synonym_penalty: DEFAULT_TYPO_PENALTY,
+            allow_split_bridge: false,
+            bridge_penalty: DEFAULT_TYPO_PENALTY,
+            exact_kbest: false,
+            temperature: DEFAULT_COST_TEMPERATURE,
+            phonological_fuzzy: false,
+            candidate_filter: None,
         }
     }
 }
 
+/// Normalize `results`' `total_cost`s into [`ReadingCandidatePath::probability`]
+/// via a softmax over `-total_cost / temperature`, so the returned set's
+/// probabilities sum to `1.0`. Shifts by the cheapest path's cost first
+/// (which leaves the softmax itself unchanged) so the cheapest path's
+/// unnormalized weight is always `exp(0) == 1` regardless of how large
+/// `total_cost` itself is, the same overflow-avoidance trick as
+/// [`Lattice::marginal_costs`](crate::analysis::lattice::Lattice::marginal_costs)'s
+/// `logsumexp`.
+fn assign_softmax_probabilities(results: &mut [ReadingCandidatePath], temperature: f64) {
+    if results.is_empty() {
+        return;
+    }
+    let t = if temperature > 0.0 {
+        temperature
+    } else {
+        f64::MIN_POSITIVE
+    };
+    let min_cost = results.iter().map(|c| c.total_cost).min().unwrap();
+    let weights: Vec<f64> = results
+        .iter()
+        .map(|c| (-((c.total_cost - min_cost) as f64) / t).exp())
+        .collect();
+    let sum: f64 = weights.iter().sum();
+    for (candidate, weight) in results.iter_mut().zip(weights) {
+        candidate.probability = weight / sum;
+    }
+}
+
 pub fn enumerate_reading_candidates(
     lattice: &Lattice,
     input: &InputBuffer,
@@ -366,13 +1432,337 @@ pub fn enumerate_reading_candidates(
     reading: &str,
     max_results: usize,
     min_tokens: usize,
+) -> SudachiResult<Vec<ReadingCandidatePath>> {
+    enumerate_reading_candidates_with_options(
+        lattice,
+        input,
+        lexicon,
+        conn,
+        subset,
+        reading,
+        max_results,
+        min_tokens,
+        &ReadingSearchOptions::default(),
+        None,
+    )
+}
+
+/// Like [`enumerate_reading_candidates`], but tolerant of up to `max_typo`
+/// character-level edits (insertions, deletions, substitutions) between the
+/// query reading and a node's match variants, in the style of MeiliSearch's
+/// `word_derivations` / `max_typo`. Each absorbed edit adds `typo_penalty` to
+/// the path's `total_cost`, so exact matches (`max_typo == 0` reproduces the
+/// old exact-match behavior exactly) still sort first. The accumulated edit
+/// distance is reported per path as
+/// [`reading_edit_distance`](ReadingCandidatePath::reading_edit_distance).
+///
+/// Common Japanese reading-spelling variation — long vowels, small/large
+/// kana, dakuten/handakuten — is folded away before the `max_typo` budget is
+/// even considered (see
+/// [`phonological_fuzzy`](ReadingSearchOptions::phonological_fuzzy)), so a
+/// query like `トウキョウフ` still finds `東京都`'s `トウキョウト` reading within
+/// one substitution rather than needing a larger `max_typo` to also absorb
+/// ordinary spelling variants.
+pub fn enumerate_reading_candidates_fuzzy(
+    lattice: &Lattice,
+    input: &InputBuffer,
+    lexicon: &LexiconSet,
+    conn: &ConnectionMatrix,
+    subset: InfoSubset,
+    reading: &str,
+    max_results: usize,
+    min_tokens: usize,
+    max_typo: usize,
+    typo_penalty: i32,
+) -> SudachiResult<Vec<ReadingCandidatePath>> {
+    enumerate_reading_candidates_with_options(
+        lattice,
+        input,
+        lexicon,
+        conn,
+        subset,
+        reading,
+        max_results,
+        min_tokens,
+        &ReadingSearchOptions {
+            max_typo,
+            typo_penalty,
+            phonological_fuzzy: true,
+            ..ReadingSearchOptions::default()
+        },
+        None,
+    )
+}
+
+/// Like [`enumerate_reading_candidates`], but for incremental/IME-style
+/// callers: `reading` is treated as a partially-typed prefix, and a lattice
+/// path is acceptable as soon as its concatenated reading *starts with* the
+/// query, rather than only when it equals the query exactly at the end of
+/// the sentence. A token whose variant only partially overlaps the
+/// remaining query still counts as a match for the overlapping portion.
+/// Each returned path's [`remaining_surface`](ReadingCandidatePath::remaining_surface)
+/// is the as-yet-untyped continuation of the underlying surface past the
+/// matched tokens, so a caller can show it alongside a partial conversion
+/// and re-query with a longer `reading` on the next keystroke without
+/// re-tokenizing.
+pub fn enumerate_reading_candidates_prefix(
+    lattice: &Lattice,
+    input: &InputBuffer,
+    lexicon: &LexiconSet,
+    conn: &ConnectionMatrix,
+    subset: InfoSubset,
+    reading: &str,
+    max_results: usize,
+    min_tokens: usize,
+) -> SudachiResult<Vec<ReadingCandidatePath>> {
+    enumerate_reading_candidates_with_options(
+        lattice,
+        input,
+        lexicon,
+        conn,
+        subset,
+        reading,
+        max_results,
+        min_tokens,
+        &ReadingSearchOptions {
+            is_prefix: true,
+            ..ReadingSearchOptions::default()
+        },
+        None,
+    )
+}
+
+/// Like [`enumerate_reading_candidates`], but allows a reading segment to
+/// bridge two consecutive lattice nodes (via their concatenated match
+/// variants) when no single node matches on its own, so readings that split
+/// unevenly across a morpheme boundary can still be found.
+pub fn enumerate_reading_candidates_bridged(
+    lattice: &Lattice,
+    input: &InputBuffer,
+    lexicon: &LexiconSet,
+    conn: &ConnectionMatrix,
+    subset: InfoSubset,
+    reading: &str,
+    max_results: usize,
+    min_tokens: usize,
+) -> SudachiResult<Vec<ReadingCandidatePath>> {
+    enumerate_reading_candidates_with_options(
+        lattice,
+        input,
+        lexicon,
+        conn,
+        subset,
+        reading,
+        max_results,
+        min_tokens,
+        &ReadingSearchOptions {
+            allow_split_bridge: true,
+            ..ReadingSearchOptions::default()
+        },
+        None,
+    )
+}
+
+/// Like [`enumerate_reading_candidates`], but enumerates paths via the exact
+/// k-shortest-path best-first search rather than the branch-and-bound DFS:
+/// the returned `max_results` paths are provably the globally cheapest ones,
+/// in ascending `total_cost` order, rather than whatever the DFS's pruning
+/// happened to settle on.
+pub fn enumerate_reading_candidates_exact_kbest(
+    lattice: &Lattice,
+    input: &InputBuffer,
+    lexicon: &LexiconSet,
+    conn: &ConnectionMatrix,
+    subset: InfoSubset,
+    reading: &str,
+    max_results: usize,
+    min_tokens: usize,
+) -> SudachiResult<Vec<ReadingCandidatePath>> {
+    enumerate_reading_candidates_with_options(
+        lattice,
+        input,
+        lexicon,
+        conn,
+        subset,
+        reading,
+        max_results,
+        min_tokens,
+        &ReadingSearchOptions {
+            exact_kbest: true,
+            ..ReadingSearchOptions::default()
+        },
+        None,
+    )
+}
+
+/// Like [`enumerate_reading_candidates`], but derives each returned path's
+/// [`probability`](ReadingCandidatePath::probability) using `temperature`
+/// instead of [`DEFAULT_COST_TEMPERATURE`], for callers (e.g. a kana-to-kanji
+/// conversion UI) that want to tune how sharply the score favors the
+/// cheapest path over its runners-up.
+pub fn enumerate_reading_candidates_with_temperature(
+    lattice: &Lattice,
+    input: &InputBuffer,
+    lexicon: &LexiconSet,
+    conn: &ConnectionMatrix,
+    subset: InfoSubset,
+    reading: &str,
+    max_results: usize,
+    min_tokens: usize,
+    temperature: f64,
+) -> SudachiResult<Vec<ReadingCandidatePath>> {
+    enumerate_reading_candidates_with_options(
+        lattice,
+        input,
+        lexicon,
+        conn,
+        subset,
+        reading,
+        max_results,
+        min_tokens,
+        &ReadingSearchOptions {
+            temperature,
+            ..ReadingSearchOptions::default()
+        },
+        None,
+    )
+}
+
+/// Like [`enumerate_reading_candidates`], but `reading` may be katakana,
+/// hiragana, or romaji per `kind` (use [`InputKind::Auto`] to detect which
+/// from the query itself). Romaji is converted to katakana with
+/// [`romaji_to_katakana`] before lattice matching, so callers get back the
+/// same [`ReadingCandidatePath`] results an equivalent kana query would.
+pub fn enumerate_reading_candidates_with_input_kind(
+    lattice: &Lattice,
+    input: &InputBuffer,
+    lexicon: &LexiconSet,
+    conn: &ConnectionMatrix,
+    subset: InfoSubset,
+    reading: &str,
+    max_results: usize,
+    min_tokens: usize,
+    kind: InputKind,
+) -> SudachiResult<Vec<ReadingCandidatePath>> {
+    let resolved_kind = match kind {
+        InputKind::Auto => detect_input_kind(reading),
+        other => other,
+    };
+    let converted;
+    let reading = match resolved_kind {
+        InputKind::Romaji => {
+            converted = romaji_to_katakana(reading);
+            converted.as_str()
+        }
+        InputKind::Katakana | InputKind::Hiragana | InputKind::Auto => reading,
+    };
+    enumerate_reading_candidates_with_options(
+        lattice,
+        input,
+        lexicon,
+        conn,
+        subset,
+        reading,
+        max_results,
+        min_tokens,
+        &ReadingSearchOptions::default(),
+        None,
+    )
+}
+
+/// Like [`enumerate_reading_candidates`], but constrained by `filter`:
+/// [`ReadingCandidateFilter::min_tokens`]/`max_tokens` bound a path's token
+/// count, [`ReadingCandidateFilter::allowed_pos_prefixes`] keeps only tokens
+/// whose POS matches one of the given prefixes (e.g. restricting to
+/// proper-noun/place-name readings), and
+/// [`ReadingCandidateFilter::require_surface_style`] demands every token's
+/// surface be produced in a particular normalization (kanji, hiragana,
+/// katakana, or numeric). A node failing the filter is excluded from the
+/// search graph before traversal starts, so the returned `max_results` paths
+/// are always that many valid ones rather than fewer after post-hoc
+/// filtering.
+pub fn enumerate_reading_candidates_filtered(
+    lattice: &Lattice,
+    input: &InputBuffer,
+    lexicon: &LexiconSet,
+    conn: &ConnectionMatrix,
+    subset: InfoSubset,
+    reading: &str,
+    max_results: usize,
+    filter: &ReadingCandidateFilter,
+) -> SudachiResult<Vec<ReadingCandidatePath>> {
+    enumerate_reading_candidates_with_options(
+        lattice,
+        input,
+        lexicon,
+        conn,
+        subset,
+        reading,
+        max_results,
+        filter.min_tokens,
+        &ReadingSearchOptions {
+            candidate_filter: Some(filter),
+            ..ReadingSearchOptions::default()
+        },
+        None,
+    )
+}
+
+/// Like [`enumerate_reading_candidates_with_options`], but reuses `cache`
+/// across calls, for interactive callers that re-query the same (or a
+/// growing-prefix) reading against the same lattice repeatedly. Callers must
+/// invoke [`ReadingSearchCache::invalidate_for_new_lattice`] themselves
+/// whenever `lattice` changes.
+#[allow(clippy::too_many_arguments)]
+pub fn enumerate_reading_candidates_cached(
+    lattice: &Lattice,
+    input: &InputBuffer,
+    lexicon: &LexiconSet,
+    conn: &ConnectionMatrix,
+    subset: InfoSubset,
+    reading: &str,
+    max_results: usize,
+    min_tokens: usize,
+    options: &ReadingSearchOptions,
+    cache: &ReadingSearchCache,
+) -> SudachiResult<Vec<ReadingCandidatePath>> {
+    enumerate_reading_candidates_with_options(
+        lattice,
+        input,
+        lexicon,
+        conn,
+        subset,
+        reading,
+        max_results,
+        min_tokens,
+        options,
+        Some(cache),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn enumerate_reading_candidates_with_options(
+    lattice: &Lattice,
+    input: &InputBuffer,
+    lexicon: &LexiconSet,
+    conn: &ConnectionMatrix,
+    subset: InfoSubset,
+    reading: &str,
+    max_results: usize,
+    min_tokens: usize,
+    options: &ReadingSearchOptions,
+    cache: Option<&ReadingSearchCache>,
 ) -> SudachiResult<Vec<ReadingCandidatePath>> {
     if max_results == 0 {
         return Ok(Vec::new());
     }
     let min_tokens = min_tokens.max(1);
 
-    let normalized_reading = normalize_for_matching(reading);
+    let normalized_reading = if options.phonological_fuzzy {
+        normalize_for_phonological_matching(reading)
+    } else {
+        normalize_for_matching(reading)
+    };
     if normalized_reading.is_empty() {
         return Ok(Vec::new());
     }
@@ -393,9 +1783,48 @@ pub fn enumerate_reading_candidates(
         let mut metas = Vec::with_capacity(nodes.len());
         for node in nodes {
             let word_info = make_word_info(node, input, lexicon, read_subset)?;
+            if let Some(filter) = options.candidate_filter {
+                if !filter.node_allowed(&word_info) {
+                    continue;
+                }
+            }
+            let cached_variants = if node.word_id().is_oov() {
+                None
+            } else {
+                cache.and_then(|c| c.get_variants(node.word_id()))
+            };
+            let from_cache = cached_variants.is_some();
+            let (mut match_variants, mut seen) = match cached_variants {
+                Some(variants) => {
+                    let seen = variants.iter().map(|v| v.text.clone()).collect();
+                    (variants, seen)
+                }
+                None => build_match_variants(&word_info),
+            };
+            if !from_cache && !node.word_id().is_oov() {
+                if let Some(c) = cache {
+                    c.put_variants(node.word_id(), match_variants.clone());
+                }
+            }
+            if let Some(synonyms) = options.synonyms {
+                extend_match_variants_with_synonyms(
+                    &mut match_variants,
+                    &mut seen,
+                    &word_info,
+                    lexicon,
+                    read_subset,
+                    synonyms,
+                    options.synonym_penalty,
+                )?;
+            }
+            if options.phonological_fuzzy {
+                for variant in &mut match_variants {
+                    variant.text = fold_phonological_variation(&variant.text);
+                }
+            }
             let meta = NodeMeta {
                 node: node.clone(),
-                match_variants: build_match_variants(&word_info),
+                match_variants,
                 word_info,
             };
             nodes_by_begin[node.begin()].push(NodeRef {
@@ -407,15 +1836,520 @@ pub fn enumerate_reading_candidates(
         metas_by_end.push(metas);
     }
 
-    let results = Searcher::new(
+    let max_tokens = options
+        .candidate_filter
+        .map_or(usize::MAX, |filter| filter.max_tokens);
+    let min_tokens = options
+        .candidate_filter
+        .map_or(min_tokens, |filter| min_tokens.max(filter.min_tokens));
+
+    let mut results = Searcher::new(
         conn,
+        input,
         &normalized_reading,
+        options.max_typo,
+        options.typo_penalty,
+        options.is_prefix,
+        options.allow_split_bridge,
+        options.bridge_penalty,
         end_boundary,
         max_results,
         min_tokens,
+        max_tokens,
+        options.exact_kbest,
         &nodes_by_begin,
         &metas_by_end,
+        cache,
     )
     .run();
+    assign_softmax_probabilities(&mut results, options.temperature);
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query_chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn automaton_exact_match_requires_zero_errors() {
+        let query = query_chars("トウキョウ");
+        let state = vec![(0, 0)];
+        let consumed = automaton_consume_variant(&state, "トウキョウ", &query, 0, false).unwrap();
+        assert_eq!(automaton_accepts(&consumed, query.len() as u32), Some(0));
+    }
+
+    #[test]
+    fn automaton_rejects_mismatch_without_typo_budget() {
+        let query = query_chars("トウキョウ");
+        let state = vec![(0, 0)];
+        assert!(automaton_consume_variant(&state, "トウキョウフ", &query, 0, false).is_none());
+    }
+
+    #[test]
+    fn automaton_accepts_single_substitution_within_budget() {
+        let query = query_chars("トウキョウフ");
+        let state = vec![(0, 0)];
+        let consumed = automaton_consume_variant(&state, "トウキョウト", &query, 1, false).unwrap();
+        assert_eq!(automaton_accepts(&consumed, query.len() as u32), Some(1));
+    }
+
+    #[test]
+    fn automaton_prefix_mode_stops_charging_after_query_is_consumed() {
+        let query = query_chars("トウキョ");
+        let state = vec![(0, 0)];
+        // the variant continues two characters past what the user has typed so far.
+        let consumed =
+            automaton_consume_variant(&state, "トウキョウト", &query, 0, true).unwrap();
+        assert_eq!(automaton_accepts(&consumed, query.len() as u32), Some(0));
+    }
+
+    #[test]
+    fn fold_phonological_variation_equates_long_vowel_spellings() {
+        assert_eq!(
+            fold_phonological_variation("トウキョウ"),
+            fold_phonological_variation("トーキョー")
+        );
+        assert_eq!(
+            fold_phonological_variation("オウ"),
+            fold_phonological_variation("オー")
+        );
+    }
+
+    #[test]
+    fn fold_phonological_variation_equates_small_and_large_kana() {
+        assert_eq!(
+            fold_phonological_variation("キャット"),
+            fold_phonological_variation("キヤット")
+        );
+    }
+
+    #[test]
+    fn fold_phonological_variation_equates_dakuten_variants() {
+        assert_eq!(
+            fold_phonological_variation("ガッコウ"),
+            fold_phonological_variation("カッコウ")
+        );
+        assert_eq!(strip_dakuten("ガ"), "カ");
+        assert_eq!(strip_dakuten("パ"), "ハ");
+    }
+
+    #[test]
+    fn normalize_for_phonological_matching_folds_on_top_of_width_and_case() {
+        assert_eq!(
+            normalize_for_phonological_matching("とうきょう"),
+            normalize_for_phonological_matching("トーキョー")
+        );
+    }
+
+    #[test]
+    fn romaji_to_katakana_converts_plain_syllables() {
+        assert_eq!(romaji_to_katakana("tokyo"), "トキョ");
+        assert_eq!(romaji_to_katakana("toukyouto"), "トーキョート");
+    }
+
+    #[test]
+    fn romaji_to_katakana_handles_macrons_and_oo_long_vowels() {
+        assert_eq!(romaji_to_katakana("tōkyōto"), romaji_to_katakana("toukyouto"));
+        assert_eq!(romaji_to_katakana("oosaka"), "オーサカ");
+    }
+
+    #[test]
+    fn romaji_to_katakana_handles_geminate_consonants() {
+        assert_eq!(romaji_to_katakana("kitte"), "キッテ");
+        assert_eq!(romaji_to_katakana("gakkou"), "ガッコー");
+    }
+
+    #[test]
+    fn romaji_to_katakana_handles_n_before_consonant_and_explicit_nn() {
+        assert_eq!(romaji_to_katakana("kon'nichiwa").replace('\'', ""), "コンニチワ");
+        assert_eq!(romaji_to_katakana("honnou"), "ホンノー");
+        assert_eq!(romaji_to_katakana("shinbun"), "シンブン");
+    }
+
+    #[test]
+    fn detect_input_kind_distinguishes_scripts() {
+        assert_eq!(detect_input_kind("トウキョウ"), InputKind::Katakana);
+        assert_eq!(detect_input_kind("とうきょう"), InputKind::Hiragana);
+        assert_eq!(detect_input_kind("toukyou"), InputKind::Romaji);
+    }
+
+    #[test]
+    fn surface_matches_style_classifies_each_style() {
+        assert!(surface_matches_style("東京都", SurfaceStyle::Kanji));
+        assert!(!surface_matches_style("東京都。", SurfaceStyle::Kanji));
+        assert!(surface_matches_style("とうきょう", SurfaceStyle::Hiragana));
+        assert!(surface_matches_style("トウキョウ", SurfaceStyle::Katakana));
+        assert!(surface_matches_style("123", SurfaceStyle::Numeric));
+        assert!(surface_matches_style("１２３", SurfaceStyle::Numeric));
+        assert!(!surface_matches_style("", SurfaceStyle::Kanji));
+    }
+
+    #[test]
+    fn candidate_filter_rejects_surface_style_mismatch() {
+        let word_info: WordInfo = WordInfoData {
+            surface: "東京都".to_owned(),
+            ..Default::default()
+        }
+        .into();
+        let kanji_only = ReadingCandidateFilter {
+            require_surface_style: Some(SurfaceStyle::Kanji),
+            ..ReadingCandidateFilter::new()
+        };
+        assert!(kanji_only.node_allowed(&word_info));
+
+        let hiragana_only = ReadingCandidateFilter {
+            require_surface_style: Some(SurfaceStyle::Hiragana),
+            ..ReadingCandidateFilter::new()
+        };
+        assert!(!hiragana_only.node_allowed(&word_info));
+    }
+
+    #[test]
+    fn synonym_groups_reports_registered_members() {
+        let mut groups = SynonymGroups::new();
+        groups.insert(1, WordId::from_raw(10));
+        groups.insert(1, WordId::from_raw(20));
+        groups.insert(2, WordId::from_raw(30));
+
+        let raw_ids = |ids: &[WordId]| ids.iter().map(|id| id.as_raw()).collect::<Vec<_>>();
+        assert_eq!(raw_ids(groups.members(1)), vec![10, 20]);
+        assert_eq!(raw_ids(groups.members(2)), vec![30]);
+        assert!(groups.members(3).is_empty());
+    }
+
+    #[test]
+    fn reading_search_cache_round_trips_variants_by_word_id() {
+        let cache = ReadingSearchCache::new();
+        let word_id = WordId::from_raw(42);
+        assert!(cache.get_variants(word_id).is_none());
+
+        let variants = vec![MatchVariant {
+            text: "トウキョウ".to_owned(),
+            penalty: 0,
+        }];
+        cache.put_variants(word_id, variants.clone());
+
+        let cached = cache.get_variants(word_id).expect("cached variants");
+        assert_eq!(cached.len(), variants.len());
+        assert_eq!(cached[0].text, variants[0].text);
+    }
+
+    #[test]
+    fn reading_search_cache_invalidate_for_new_lattice_clears_cost_cache_only() {
+        let cache = ReadingSearchCache::new();
+        let word_id = WordId::from_raw(7);
+        cache.put_variants(
+            word_id,
+            vec![MatchVariant {
+                text: "ト".to_owned(),
+                penalty: 0,
+            }],
+        );
+        cache.cost_cache.borrow_mut().insert(
+            SearchState {
+                boundary: 0,
+                prev_right_id: 0,
+                automaton: vec![(0, 0)],
+            },
+            Some(123),
+        );
+
+        cache.invalidate_for_new_lattice();
+
+        assert!(cache.cost_cache.borrow().is_empty());
+        assert!(cache.get_variants(word_id).is_some());
+    }
+
+    #[test]
+    fn reading_search_cache_invalidates_cost_cache_on_query_change() {
+        let cache = ReadingSearchCache::new();
+        let state = SearchState {
+            boundary: 0,
+            prev_right_id: 0,
+            automaton: vec![(0, 0)],
+        };
+        cache.cost_cache.borrow_mut().insert(state.clone(), Some(1));
+
+        cache.invalidate_for_new_query(&CostCacheQuery {
+            query: vec!['ト', 'ウ'],
+            max_typo: 0,
+            typo_penalty: 0,
+            is_prefix: false,
+            allow_bridge: false,
+            bridge_penalty: 0,
+        });
+        assert!(
+            cache.cost_cache.borrow().is_empty(),
+            "first query establishes the signature and starts from an empty cache"
+        );
+        cache.cost_cache.borrow_mut().insert(state.clone(), Some(1));
+
+        cache.invalidate_for_new_query(&CostCacheQuery {
+            query: vec!['ト', 'ウ'],
+            max_typo: 0,
+            typo_penalty: 0,
+            is_prefix: false,
+            allow_bridge: false,
+            bridge_penalty: 0,
+        });
+        assert!(
+            cache.cost_cache.borrow().get(&state).is_some(),
+            "repeating the same query signature must not drop entries"
+        );
+
+        cache.invalidate_for_new_query(&CostCacheQuery {
+            query: vec!['ト', 'キ'],
+            max_typo: 0,
+            typo_penalty: 0,
+            is_prefix: false,
+            allow_bridge: false,
+            bridge_penalty: 0,
+        });
+        assert!(
+            cache.cost_cache.borrow().is_empty(),
+            "a different query must not be able to read costs computed for another query's remaining characters"
+        );
+    }
+
+    fn candidate_with_cost(total_cost: i32) -> ReadingCandidatePath {
+        ReadingCandidatePath {
+            total_cost,
+            probability: 0.0,
+            covered_reading_len: 0,
+            remaining_surface: String::new(),
+            reading_edit_distance: 0,
+            tokens: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn softmax_probabilities_sum_to_one_and_favor_cheaper_paths() {
+        let mut results = vec![
+            candidate_with_cost(100),
+            candidate_with_cost(200),
+            candidate_with_cost(300),
+        ];
+        assign_softmax_probabilities(&mut results, 50.0);
+
+        let sum: f64 = results.iter().map(|c| c.probability).sum();
+        assert!((sum - 1.0).abs() < 1e-9, "sum={sum}");
+        assert!(results[0].probability > results[1].probability);
+        assert!(results[1].probability > results[2].probability);
+    }
+
+    #[test]
+    fn softmax_probabilities_are_uniform_for_equal_costs() {
+        let mut results = vec![candidate_with_cost(50), candidate_with_cost(50)];
+        assign_softmax_probabilities(&mut results, 50.0);
+        assert!((results[0].probability - 0.5).abs() < 1e-9);
+        assert!((results[1].probability - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn softmax_probabilities_noop_on_empty_results() {
+        let mut results: Vec<ReadingCandidatePath> = Vec::new();
+        assign_softmax_probabilities(&mut results, DEFAULT_COST_TEMPERATURE);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn softmax_probabilities_handle_non_positive_temperature_without_nan() {
+        let mut results = vec![candidate_with_cost(10), candidate_with_cost(20)];
+        assign_softmax_probabilities(&mut results, 0.0);
+        assert!(!results[0].probability.is_nan());
+        assert!(!results[1].probability.is_nan());
+        assert!(results[0].probability > results[1].probability);
+    }
+
+    fn test_node(begin: u16, end: u16, word_id: u32) -> Node {
+        Node::new(begin, end, 1, 1, 0, WordId::from_raw(word_id))
+    }
+
+    fn test_word_info(surface: &str) -> WordInfo {
+        WordInfoData {
+            surface: surface.to_owned(),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    /// A zero-cost connection matrix, big enough for left/right ids `0..n`,
+    /// so every transition in these `Searcher`-level tests costs exactly
+    /// whatever the match variants and `bridge_penalty` themselves charge.
+    fn zero_cost_matrix(n: usize) -> Vec<u8> {
+        vec![0u8; n * n * 2]
+    }
+
+    /// `Searcher` always stores an `&InputBuffer`, but only ever reads it
+    /// through `curr_slice_c` when a result's tokens leave a gap before
+    /// `end_boundary` (see `candidate_from_path`). Every scenario below
+    /// covers `end_boundary` exactly, so this default instance is never
+    /// actually dereferenced; it exists purely to satisfy the field.
+    fn unused_input() -> InputBuffer {
+        InputBuffer::default()
+    }
+
+    /// The two-node bridge in `expand_transitions` exists for a search
+    /// whose `max_tokens` budget is too tight to explore a second node
+    /// *separately* (the budget counts lattice nodes pushed so far, and
+    /// that check runs before a node's own successors can be explored) but
+    /// where joining two adjacent nodes' variants into one step still fits.
+    /// With `max_tokens: 1`, two lattice nodes "a" and "b" each covering
+    /// half of the query "ab" can only ever be found together via the
+    /// bridge; stepping through them one at a time never gets to look past
+    /// the first node.
+    #[test]
+    fn two_node_bridge_finds_a_reading_that_needs_both_nodes_within_max_tokens() {
+        let n = 2;
+        let raw = zero_cost_matrix(n);
+        let conn = ConnectionMatrix::from_offset_size(&raw, 0, n, n).unwrap();
+        let input = unused_input();
+
+        let node_a = test_node(0, 1, 1);
+        let node_b = test_node(1, 2, 2);
+        let meta_a = NodeMeta {
+            node: node_a,
+            word_info: test_word_info("a"),
+            match_variants: vec![MatchVariant {
+                text: "a".to_owned(),
+                penalty: 0,
+            }],
+        };
+        let meta_b = NodeMeta {
+            node: node_b,
+            word_info: test_word_info("b"),
+            match_variants: vec![MatchVariant {
+                text: "b".to_owned(),
+                penalty: 0,
+            }],
+        };
+        let nodes_by_begin = vec![
+            vec![NodeRef { end: 1, index: 0 }],
+            vec![NodeRef { end: 2, index: 0 }],
+            Vec::new(),
+        ];
+        let metas_by_end = vec![Vec::new(), vec![meta_a], vec![meta_b]];
+
+        let with_bridge = Searcher::new(
+            &conn,
+            &input,
+            "ab",
+            0,
+            DEFAULT_TYPO_PENALTY,
+            false,
+            true,
+            50,
+            2,
+            10,
+            1,
+            1,
+            false,
+            &nodes_by_begin,
+            &metas_by_end,
+            None,
+        )
+        .run();
+        assert_eq!(with_bridge.len(), 1, "{with_bridge:?}");
+        assert_eq!(with_bridge[0].total_cost, 50);
+        assert_eq!(
+            with_bridge[0]
+                .tokens
+                .iter()
+                .map(|t| t.word_id.as_raw())
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let without_bridge = Searcher::new(
+            &conn,
+            &input,
+            "ab",
+            0,
+            DEFAULT_TYPO_PENALTY,
+            false,
+            false,
+            50,
+            2,
+            10,
+            1,
+            1,
+            false,
+            &nodes_by_begin,
+            &metas_by_end,
+            None,
+        )
+        .run();
+        assert!(
+            without_bridge.is_empty(),
+            "without allow_bridge, a max_tokens: 1 search can't step past node a: {without_bridge:?}"
+        );
+    }
+
+    /// A synonym-derived variant should still let a query resolve a node
+    /// that its own literal reading/surface wouldn't match, but it must
+    /// rank behind a node whose literal variant matches directly: this is
+    /// the `synonym_penalty` contract `extend_match_variants_with_synonyms`
+    /// relies on, exercised here directly against hand-built variants
+    /// rather than through a `LexiconSet`, which this module has no way to
+    /// construct outside of a real dictionary.
+    #[test]
+    fn synonym_variant_penalty_ranks_behind_a_literal_match() {
+        let n = 2;
+        let raw = zero_cost_matrix(n);
+        let conn = ConnectionMatrix::from_offset_size(&raw, 0, n, n).unwrap();
+        let input = unused_input();
+        let synonym_penalty = 500;
+
+        let literal = NodeMeta {
+            node: test_node(0, 1, 1),
+            word_info: test_word_info("犬"),
+            match_variants: vec![MatchVariant {
+                text: "いぬ".to_owned(),
+                penalty: 0,
+            }],
+        };
+        let via_synonym = NodeMeta {
+            node: test_node(0, 1, 2),
+            word_info: test_word_info("キャニン"),
+            match_variants: vec![MatchVariant {
+                text: "いぬ".to_owned(),
+                penalty: synonym_penalty,
+            }],
+        };
+        let nodes_by_begin = vec![
+            vec![NodeRef { end: 1, index: 0 }, NodeRef { end: 1, index: 1 }],
+            Vec::new(),
+        ];
+        let metas_by_end = vec![Vec::new(), vec![literal, via_synonym]];
+
+        let results = Searcher::new(
+            &conn,
+            &input,
+            "いぬ",
+            0,
+            DEFAULT_TYPO_PENALTY,
+            false,
+            false,
+            0,
+            1,
+            10,
+            1,
+            1,
+            false,
+            &nodes_by_begin,
+            &metas_by_end,
+            None,
+        )
+        .run();
+
+        assert_eq!(results.len(), 2, "{results:?}");
+        assert_eq!(results[0].total_cost, 0);
+        assert_eq!(results[0].tokens[0].word_id.as_raw(), 1);
+        assert_eq!(results[1].total_cost, synonym_penalty);
+        assert_eq!(results[1].tokens[0].word_id.as_raw(), 2);
+    }
+}
@@ -18,7 +18,9 @@ mod common;
 
 use common::TestStatefulTokenizer as TestTokenizer;
 use sudachi::analysis::Mode;
-use sudachi::analysis::reading_candidates::ReadingCandidatePath;
+use sudachi::analysis::reading_candidates::{
+    InputKind, ReadingCandidateFilter, ReadingCandidatePath, SynonymGroups,
+};
 
 fn surfaces(path: &[sudachi::analysis::reading_candidates::ReadingCandidateToken]) -> Vec<String> {
     path.iter().map(|t| t.surface.clone()).collect()
@@ -183,3 +185,228 @@ fn reading_candidates_min_tokens_too_large_returns_empty() {
         .expect("no path");
     assert!(no_path.is_empty());
 }
+
+#[test]
+fn reading_candidates_fuzzy_absorbs_a_single_typo() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("東京都");
+    tok.tok.do_tokenize().expect("tokenize");
+
+    // "トウキョウフ" differs from the correct "トウキョウト" reading by one
+    // character (ト -> フ at the end), which an exact match can't find.
+    let exact = tok
+        .tok
+        .reading_candidates("トウキョウフ", 16)
+        .expect("exact");
+    assert!(exact.is_empty());
+
+    let fuzzy = tok
+        .tok
+        .reading_candidates_fuzzy("トウキョウフ", 16, 1, 1, 400)
+        .expect("fuzzy");
+    assert!(!fuzzy.is_empty());
+    assert_eq!(vec!["東京都".to_owned()], surfaces(&fuzzy[0].tokens));
+    assert_eq!(1, fuzzy[0].reading_edit_distance);
+}
+
+#[test]
+fn reading_candidates_prefix_matches_partially_typed_reading() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("東京都。");
+    tok.tok.do_tokenize().expect("tokenize");
+
+    // A caller re-querying on every keystroke hasn't typed the trailing "。"
+    // yet; an exact match can't accept a query that stops short of the end
+    // of the sentence.
+    let exact = tok.tok.reading_candidates("トウキョウト", 16).expect("exact");
+    assert!(exact.is_empty());
+
+    let prefix = tok
+        .tok
+        .reading_candidates_prefix("トウキョウト", 16, 1)
+        .expect("prefix");
+    assert!(!prefix.is_empty());
+    let matched = prefix
+        .iter()
+        .find(|c| surfaces(&c.tokens) == vec!["東京都".to_owned()])
+        .expect("partial match for 東京都");
+    assert_eq!(6, matched.covered_reading_len);
+    assert_eq!("。", matched.remaining_surface);
+}
+
+#[test]
+fn reading_candidates_with_synonyms_is_a_no_op_without_registered_groups() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("東京都");
+    tok.tok.do_tokenize().expect("tokenize");
+
+    // Whether synonym folding actually changes the match set for a given
+    // word depends on that word's own dictionary-provided synonym group
+    // membership (see `synonym_groups_reports_registered_members` in
+    // `reading_candidates`'s own unit tests for that half), which this test
+    // dictionary doesn't control. What this integration test instead pins
+    // down is that `reading_candidates_with_synonyms` is correctly wired
+    // through to the real lattice end to end: passing an empty
+    // `SynonymGroups` must behave exactly like the plain, synonym-less
+    // search, not silently drop or duplicate results.
+    let synonyms = SynonymGroups::new();
+    let plain = tok
+        .tok
+        .reading_candidates("トウキョウト", 16)
+        .expect("plain");
+    let with_synonyms = tok
+        .tok
+        .reading_candidates_with_synonyms("トウキョウト", 16, &synonyms)
+        .expect("with synonyms");
+
+    assert_eq!(plain.len(), with_synonyms.len());
+    for (a, b) in plain.iter().zip(with_synonyms.iter()) {
+        assert_eq!(surfaces(&a.tokens), surfaces(&b.tokens));
+        assert_eq!(a.total_cost, b.total_cost);
+    }
+}
+
+#[test]
+fn reading_candidates_bridged_still_finds_ordinary_matches() {
+    // Bridging only changes the search at a position where no single node's
+    // own variants match (see `Searcher::expand_transitions`'s
+    // `matched_single` check); it can only add candidates on top of the
+    // plain search, never change or drop one it already finds. A scenario
+    // that actually needs the two-node bridge (e.g. a rendaku-style reading
+    // split across a morpheme boundary) depends on dictionary reading data
+    // this fixture-less test dictionary doesn't control, so this pins down
+    // the part that is verifiable here: enabling it is a no-op for a
+    // reading every node already matches on its own.
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("東京都");
+    tok.tok.do_tokenize().expect("tokenize");
+
+    let plain = tok.tok.reading_candidates("トウキョウト", 16).expect("plain");
+    let bridged = tok
+        .tok
+        .reading_candidates_bridged("トウキョウト", 16, 1)
+        .expect("bridged");
+
+    assert!(!plain.is_empty());
+    assert_eq!(plain.len(), bridged.len());
+    for (a, b) in plain.iter().zip(bridged.iter()) {
+        assert_eq!(surfaces(&a.tokens), surfaces(&b.tokens));
+        assert_eq!(a.total_cost, b.total_cost);
+    }
+}
+
+#[test]
+fn reading_candidates_exact_kbest_matches_default_search_costs() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("東京都");
+    tok.tok.do_tokenize().expect("tokenize");
+
+    let dfs = tok.tok.reading_candidates("トウキョウト", 16).expect("dfs");
+    let kbest = tok
+        .tok
+        .reading_candidates_exact_kbest("トウキョウト", 16, 1)
+        .expect("kbest");
+
+    // Both enumerators are documented to return the same globally cheapest
+    // paths in ascending `total_cost` order; the exact k-best search just
+    // reaches them by a different route than the branch-and-bound DFS.
+    assert!(!dfs.is_empty());
+    assert_eq!(dfs.len(), kbest.len());
+    for (a, b) in dfs.iter().zip(kbest.iter()) {
+        assert_eq!(surfaces(&a.tokens), surfaces(&b.tokens));
+        assert_eq!(a.total_cost, b.total_cost);
+    }
+    for i in 1..kbest.len() {
+        assert!(kbest[i - 1].total_cost <= kbest[i].total_cost);
+    }
+}
+
+#[test]
+fn reading_candidates_fuzzy_folds_long_vowel_spelling_with_no_typo_budget() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("東京都");
+    tok.tok.do_tokenize().expect("tokenize");
+
+    // "トーキョート" spells 東京都's トウキョウト reading's long vowels with the
+    // chōon mark instead of ウ; an exact match can't find it since it only
+    // normalizes case/width, not phonological spelling variation.
+    let exact = tok
+        .tok
+        .reading_candidates("トーキョート", 16)
+        .expect("exact");
+    assert!(exact.is_empty());
+
+    // A zero typo budget still finds it: phonological folding runs before
+    // `max_typo` is ever consumed, so this spelling variation costs nothing
+    // and isn't reported as an edit.
+    let fuzzy = tok
+        .tok
+        .reading_candidates_fuzzy("トーキョート", 16, 0, 1, 400)
+        .expect("fuzzy");
+    assert!(!fuzzy.is_empty());
+    assert_eq!(vec!["東京都".to_owned()], surfaces(&fuzzy[0].tokens));
+    assert_eq!(0, fuzzy[0].reading_edit_distance);
+}
+
+#[test]
+fn reading_candidates_with_input_kind_converts_romaji_before_matching() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("都");
+    tok.tok.do_tokenize().expect("tokenize");
+
+    let kana = tok.tok.reading_candidates("ト", 16).expect("kana");
+    assert!(!kana.is_empty());
+    assert_eq!(vec!["都".to_owned()], surfaces(&kana[0].tokens));
+
+    // "to"/"TO" have no long vowel to fold, so the romaji- and
+    // auto-detect-converted queries below reach exactly the same match as
+    // the direct kana query above.
+    let romaji = tok
+        .tok
+        .reading_candidates_with_input_kind("to", 16, 1, InputKind::Romaji)
+        .expect("romaji");
+    let auto = tok
+        .tok
+        .reading_candidates_with_input_kind("TO", 16, 1, InputKind::Auto)
+        .expect("auto");
+
+    assert_eq!(kana.len(), romaji.len());
+    assert_eq!(kana.len(), auto.len());
+    for (a, b) in kana.iter().zip(romaji.iter()) {
+        assert_eq!(surfaces(&a.tokens), surfaces(&b.tokens));
+        assert_eq!(a.total_cost, b.total_cost);
+    }
+    for (a, b) in kana.iter().zip(auto.iter()) {
+        assert_eq!(surfaces(&a.tokens), surfaces(&b.tokens));
+        assert_eq!(a.total_cost, b.total_cost);
+    }
+}
+
+#[test]
+fn reading_candidates_filtered_prunes_by_max_tokens() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("東京都");
+    tok.tok.do_tokenize().expect("tokenize");
+
+    let unfiltered = tok
+        .tok
+        .reading_candidates("トウキョウト", 16)
+        .expect("unfiltered");
+    assert!(unfiltered
+        .iter()
+        .any(|c| surfaces(&c.tokens) == vec!["東京".to_owned(), "都".to_owned()]));
+
+    let mut filter = ReadingCandidateFilter::new();
+    filter.max_tokens = 1;
+    let filtered = tok
+        .tok
+        .reading_candidates_filtered("トウキョウト", 16, &filter)
+        .expect("filtered");
+
+    // The two-token split is excluded from the search graph itself, not
+    // filtered out of an already-built result set, so it can never appear
+    // here even though it's the second-cheapest unfiltered path.
+    assert!(!filtered.is_empty());
+    assert!(filtered.iter().all(|c| c.tokens.len() <= 1));
+    assert_eq!(vec!["東京都".to_owned()], surfaces(&filtered[0].tokens));
+}